@@ -0,0 +1,226 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::options::{check_board_size, parse_length, parse_size, parse_start};
+use crate::params::BotParams;
+use crate::sim::{Outcome, Simulation};
+use crate::snake::Spawn;
+use crate::strategy::StrategyKind;
+
+// Greedy bots can chase their tail forever, so every game is cut off eventually
+const DEFAULT_MAX_STEPS: u64 = 1_000_000;
+
+const USAGE: &str = "usage: snake bench [--strategy <name>] [--size <width>x<height>] \
+                     [--length <n>] [--start <x>,<y>] [--seeds <first>..<last>] \
+                     [--max-steps <n>] [--params <file>]";
+
+/// What to play in a headless benchmark run.
+pub struct BenchOptions {
+    strategy: StrategyKind,
+    width: usize,
+    height: usize,
+    spawn: Spawn,
+    // Half-open, one game per seed
+    seeds: std::ops::Range<u64>,
+    max_steps: u64,
+    params: BotParams,
+}
+
+impl BenchOptions {
+    /// Parses the arguments that follow `bench` on the command line.
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = BenchOptions {
+            strategy: StrategyKind::HamiltonianShortcut,
+            width: 20,
+            height: 20,
+            spawn: Spawn::default(),
+            seeds: 0..10,
+            max_steps: DEFAULT_MAX_STEPS,
+            params: BotParams::default(),
+        };
+
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} expects a value\n{}", flag, USAGE))?;
+            match flag.as_str() {
+                "--strategy" => options.strategy = StrategyKind::parse(value)?,
+                "--size" => (options.width, options.height) = parse_size(value)?,
+                "--length" => options.spawn.length = parse_length(value)?,
+                "--start" => (options.spawn.x, options.spawn.y) = parse_start(value)?,
+                "--seeds" => {
+                    let (first, last) = value
+                        .split_once("..")
+                        .and_then(|(first, last)| Some((first.parse().ok()?, last.parse().ok()?)))
+                        .filter(|(first, last)| first < last)
+                        .ok_or_else(|| {
+                            format!(
+                                "--seeds expects <first>..<last> with first < last, got {:?}",
+                                value
+                            )
+                        })?;
+                    options.seeds = first..last;
+                }
+                "--max-steps" => {
+                    options.max_steps = value
+                        .parse()
+                        .map_err(|_| format!("--max-steps expects a number, got {:?}", value))?;
+                }
+                "--params" => {
+                    options.params =
+                        BotParams::load(Path::new(value)).map_err(|err| err.to_string())?;
+                }
+                _ => return Err(format!("unknown option {:?}\n{}", flag, USAGE)),
+            }
+        }
+
+        check_board_size(options.width, options.height)?;
+        Ok(options)
+    }
+}
+
+struct GameResult {
+    seed: u64,
+    // `None` when the game hit the step cap without finishing
+    outcome: Option<Outcome>,
+    moves: u64,
+    apples: u32,
+    efficiency: f64,
+    time: Duration,
+}
+
+impl GameResult {
+    fn moves_per_apple(&self) -> f64 {
+        self.moves as f64 / self.apples.max(1) as f64
+    }
+}
+
+/// Plays one game per seed without a window and prints each result, then a
+/// summary. Returns an error if the board cannot be set up.
+pub fn run(options: &BenchOptions) -> Result<(), String> {
+    println!(
+        "{} on {}x{}, seeds {}..{}",
+        options.strategy.name(),
+        options.width,
+        options.height,
+        options.seeds.start,
+        options.seeds.end
+    );
+    println!(
+        "{:>8} {:>8} {:>10} {:>8} {:>12} {:>12} {:>10}",
+        "seed", "outcome", "moves", "apples", "moves/apple", "efficiency %", "time ms"
+    );
+
+    let mut results = Vec::new();
+    for seed in options.seeds.clone() {
+        let result = play(options, seed)?;
+        let outcome = match result.outcome {
+            Some(Outcome::Won) => "won",
+            Some(Outcome::Died(_)) => "died",
+            None => "stalled",
+        };
+        println!(
+            "{:>8} {:>8} {:>10} {:>8} {:>12.2} {:>12.1} {:>10.1}{}",
+            result.seed,
+            outcome,
+            result.moves,
+            result.apples,
+            result.moves_per_apple(),
+            result.efficiency * 100.0,
+            result.time.as_secs_f64() * 1000.0,
+            match result.outcome {
+                Some(Outcome::Died(cause)) => format!("  ({})", cause.describe()),
+                _ => String::new(),
+            }
+        );
+        results.push(result);
+    }
+
+    print_summary(&results);
+    Ok(())
+}
+
+fn play(options: &BenchOptions, seed: u64) -> Result<GameResult, String> {
+    let mut sim = Simulation::with_spawn(options.width, options.height, seed, options.spawn)
+        .map_err(|err| format!("could not set up seed {}: {}", seed, err))?;
+    let mut strategy = options.strategy.build(&options.params);
+
+    let start = Instant::now();
+    let mut moves = 0;
+    while !sim.is_over() && moves < options.max_steps {
+        sim.step(strategy.as_mut());
+        moves += 1;
+    }
+
+    Ok(GameResult {
+        seed,
+        outcome: sim.outcome(),
+        moves,
+        apples: sim.score(),
+        efficiency: sim.efficiency(),
+        time: start.elapsed(),
+    })
+}
+
+fn print_summary(results: &[GameResult]) {
+    let count = |wanted: fn(&Option<Outcome>) -> bool| {
+        results.iter().filter(|r| wanted(&r.outcome)).count()
+    };
+    println!();
+    println!(
+        "{} games: {} won, {} died, {} stalled",
+        results.len(),
+        count(|o| matches!(o, Some(Outcome::Won))),
+        count(|o| matches!(o, Some(Outcome::Died(_)))),
+        count(|o| o.is_none())
+    );
+
+    // Only finished boards say anything about how quickly the board gets filled
+    let won: Vec<&GameResult> = results
+        .iter()
+        .filter(|r| r.outcome == Some(Outcome::Won))
+        .collect();
+    println!("{:>24} {:>12} {:>12} {:>12}", "", "mean", "median", "p95");
+    print_stats(
+        "moves to fill (won)",
+        won.iter().map(|r| r.moves as f64).collect(),
+    );
+    print_stats(
+        "moves/apple",
+        results.iter().map(GameResult::moves_per_apple).collect(),
+    );
+    print_stats(
+        "efficiency %",
+        results.iter().map(|r| r.efficiency * 100.0).collect(),
+    );
+    print_stats(
+        "time ms",
+        results
+            .iter()
+            .map(|r| r.time.as_secs_f64() * 1000.0)
+            .collect(),
+    );
+}
+
+fn print_stats(label: &str, mut values: Vec<f64>) {
+    if values.is_empty() {
+        println!("{:>24} {:>12} {:>12} {:>12}", label, "-", "-", "-");
+        return;
+    }
+    values.sort_by(f64::total_cmp);
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    println!(
+        "{:>24} {:>12.1} {:>12.1} {:>12.1}",
+        label,
+        mean,
+        percentile(&values, 50.0),
+        percentile(&values, 95.0)
+    );
+}
+
+// Nearest-rank percentile of already sorted values
+fn percentile(sorted: &[f64], percent: f64) -> f64 {
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
@@ -1,31 +1,143 @@
-use macroquad::prelude::*;
-
-#[derive(PartialEq, PartialOrd, Clone, Copy)]
-pub enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+use crate::direction::Direction;
+use crate::error::SnakeError;
+use crate::rng::Rng;
+
+// The cycle's positions in tour order, and each cell's tour number indexed by x + y * width
+pub type HamiltonianCycle = (Vec<(usize, usize)>, Vec<usize>);
+
+// Tour number given to a cell that the cycle does not pass through
+pub const NOT_ON_CYCLE: usize = usize::MAX;
+
+// Builds a Hamiltonian cycle over the board by walking around a random spanning
+// tree of the half-resolution maze. An odd side is covered by splicing the last
+// row or column into the cycle two cells at a time; when both sides are odd no
+// Hamiltonian cycle exists, so the bottom-right corner (see `skipped_cell`) is
+// left out and numbered NOT_ON_CYCLE
+pub fn generate_hamiltonian_cycle(
+    width: i32,
+    height: i32,
+    rng: &mut Rng,
+) -> Result<HamiltonianCycle, SnakeError> {
+    if width < 2 || height < 2 {
+        return Err(SnakeError::BoardTooSmall { width, height });
+    }
+
+    let core_width = width - width % 2;
+    let core_height = height - height % 2;
+    let core_size = (core_width * core_height) as usize;
+    let tour_to_number = generate_maze_tour(core_width, core_height, rng);
+    let positions = get_cycle_positions(&tour_to_number, core_width, core_height, core_size);
+
+    if core_width == width && core_height == height {
+        // Return both the positions and the tour numbers
+        return Ok((positions, tour_to_number));
+    }
+
+    let positions = splice_odd_edges(&positions, width as usize, height as usize);
+    let tour_to_number = number_positions(&positions, width as usize, height as usize);
+
+    Ok((positions, tour_to_number))
 }
 
-pub fn generate_hamiltonian_cycle(width: i32, height: i32) -> (Vec<(usize, usize)>, Vec<usize>) {
-    if width != height {
-        panic!("Width and height must be equal");
+// The one cell left off the cycle on a board with two odd sides
+pub fn skipped_cell(width: i32, height: i32) -> Option<(usize, usize)> {
+    if width % 2 != 0 && height % 2 != 0 {
+        Some(((width - 1) as usize, (height - 1) as usize))
+    } else {
+        None
     }
+}
 
-    if width % 2 != 0 || height % 2 != 0 {
-        panic!("Width and height must be even");
+// Checks that `cycle` is a Hamiltonian cycle of the board, stepping from cell
+// to neighbouring cell and back round to the start through every cell but the
+// one `skipped_cell` leaves out, and that `tour_numbers` gives each cell its
+// place along it. For cycles read back from files
+pub fn check_cycle(
+    cycle: &[(usize, usize)],
+    tour_numbers: &[usize],
+    width: usize,
+    height: usize,
+) -> Result<(), String> {
+    if tour_numbers.len() != width * height {
+        return Err(format!(
+            "expected {} tour numbers, found {}",
+            width * height,
+            tour_numbers.len()
+        ));
+    }
+    for (i, &(x, y)) in cycle.iter().enumerate() {
+        if x >= width || y >= height || tour_numbers[x + y * width] != i {
+            return Err(format!(
+                "cycle cell {:?} does not match its tour number",
+                (x, y)
+            ));
+        }
     }
+    let skipped = skipped_cell(width as i32, height as i32);
+    for (i, &number) in tour_numbers.iter().enumerate() {
+        let cell = (i % width, i / width);
+        if number == NOT_ON_CYCLE && skipped != Some(cell) {
+            return Err(format!("cell {:?} is not on the cycle", cell));
+        }
+        if number != NOT_ON_CYCLE && (skipped == Some(cell) || cycle.get(number) != Some(&cell)) {
+            return Err(format!(
+                "cell {:?} has tour number {} but is not that far along the cycle",
+                cell, number
+            ));
+        }
+    }
+    for (i, &a) in cycle.iter().enumerate() {
+        let b = cycle[(i + 1) % cycle.len()];
+        if a.0.abs_diff(b.0) + a.1.abs_diff(b.1) != 1 {
+            return Err(format!("the cycle jumps from {:?} to {:?}", a, b));
+        }
+    }
+    Ok(())
+}
 
-    let arena_size = (width * height) as usize;
-    let tour_to_number = generate_maze_tour(width, height);
-    let positions = get_cycle_positions(&tour_to_number, width, height, arena_size);
+// Extend a cycle over the even core of the board into an odd last row and/or
+// column. Every 2x2 block on the edge of the core keeps its outer edge in the
+// cycle, so each such edge is replaced by a detour through the two cells beyond it
+fn splice_odd_edges(core: &[(usize, usize)], width: usize, height: usize) -> Vec<(usize, usize)> {
+    let mut positions = Vec::with_capacity(width * height);
+
+    for i in 0..core.len() {
+        let a = core[i];
+        let b = core[(i + 1) % core.len()];
+        positions.push(a);
+
+        if !height.is_multiple_of(2)
+            && a.1 == height - 2
+            && b.1 == height - 2
+            && a.0.min(b.0).is_multiple_of(2)
+        {
+            positions.push((a.0, height - 1));
+            positions.push((b.0, height - 1));
+        }
+
+        if !width.is_multiple_of(2)
+            && a.0 == width - 2
+            && b.0 == width - 2
+            && a.1.min(b.1).is_multiple_of(2)
+        {
+            positions.push((width - 1, a.1));
+            positions.push((width - 1, b.1));
+        }
+    }
 
-    // Return both the positions and the tour numbers
-    (positions, tour_to_number)
+    positions
+}
+
+// Number each cell by its index along the cycle
+fn number_positions(positions: &[(usize, usize)], width: usize, height: usize) -> Vec<usize> {
+    let mut tour_to_number = vec![NOT_ON_CYCLE; width * height];
+    for (number, &(x, y)) in positions.iter().enumerate() {
+        tour_to_number[x + y * width] = number;
+    }
+    tour_to_number
 }
 
-fn generate_maze_tour(width: i32, height: i32) -> Vec<usize> {
+fn generate_maze_tour(width: i32, height: i32, rng: &mut Rng) -> Vec<usize> {
     let arena_size = (width * height) as usize;
     let mut tour_to_number = vec![0; arena_size];
 
@@ -42,29 +154,70 @@ fn generate_maze_tour(width: i32, height: i32) -> Vec<usize> {
         maze_size
     ];
 
-    generate_maze_paths(&mut nodes, (-1, -1), (0, 0), maze_width, maze_height);
+    generate_maze_paths(&mut nodes, rng, maze_width, maze_height);
 
     generate_tour_numbers(&nodes, &mut tour_to_number, width, arena_size);
 
     tour_to_number
 }
 
-// Recursively generate maze paths
-fn generate_maze_paths(
+// Generate maze paths as a randomised depth-first spanning tree. The search keeps
+// its own stack instead of recursing so large boards cannot overflow the call
+// stack; each frame records which of its six neighbour visits comes next (two
+// random picks, then all four directions in a fixed order), so a seed produces
+// the same maze as a recursive walk would
+fn generate_maze_paths(nodes: &mut [MazeNode], rng: &mut Rng, maze_width: i32, maze_height: i32) {
+    let mut stack: Vec<((i32, i32), u8)> = Vec::new();
+
+    if enter_maze_node(nodes, (-1, -1), (0, 0), maze_width, maze_height) {
+        stack.push(((0, 0), 0));
+    }
+
+    while let Some((cur, next_visit)) = stack.last_mut() {
+        let (x, y) = *cur;
+        let target = match *next_visit {
+            // Visit the four connected nodes randomly
+            0 | 1 => match rng.gen_range(0, 4) {
+                0 => (x - 1, y),
+                1 => (x + 1, y),
+                2 => (x, y - 1),
+                _ => (x, y + 1),
+            },
+            // Visit all remaining directions
+            2 => (x - 1, y),
+            3 => (x + 1, y),
+            4 => (x, y + 1),
+            5 => (x, y - 1),
+            _ => {
+                stack.pop();
+                continue;
+            }
+        };
+        *next_visit += 1;
+
+        if enter_maze_node(nodes, (x, y), target, maze_width, maze_height) {
+            stack.push((target, 0));
+        }
+    }
+}
+
+// Step from one maze node into a neighbour, opening the wall between them.
+// Returns false if the neighbour is off the maze or already part of the tree
+fn enter_maze_node(
     nodes: &mut [MazeNode],
     from: (i32, i32),
     cur: (i32, i32),
     maze_width: i32,
     maze_height: i32,
-) {
+) -> bool {
     let (from_x, from_y) = from;
     let (x, y) = cur;
 
     if x < 0 || y < 0 || x >= maze_width || y >= maze_height {
-        return;
+        return false;
     }
     if MazeNode::is_visited(nodes, cur, maze_width) {
-        return;
+        return false;
     }
     MazeNode::mark_visited(nodes, cur, maze_width);
 
@@ -80,23 +233,7 @@ fn generate_maze_paths(
         }
     }
 
-    // Visit the four connected nodes randomly
-    for _ in 0..2 {
-        let r = rand::gen_range(0, 4);
-        match r {
-            0 => generate_maze_paths(nodes, cur, (x - 1, y), maze_width, maze_height),
-            1 => generate_maze_paths(nodes, cur, (x + 1, y), maze_width, maze_height),
-            2 => generate_maze_paths(nodes, cur, (x, y - 1), maze_width, maze_height),
-            3 => generate_maze_paths(nodes, cur, (x, y + 1), maze_width, maze_height),
-            _ => {}
-        }
-    }
-
-    // Visit all remaining directions
-    generate_maze_paths(nodes, cur, (x - 1, y), maze_width, maze_height);
-    generate_maze_paths(nodes, cur, (x + 1, y), maze_width, maze_height);
-    generate_maze_paths(nodes, cur, (x, y + 1), maze_width, maze_height);
-    generate_maze_paths(nodes, cur, (x, y - 1), maze_width, maze_height);
+    true
 }
 
 // Generate the tour numbers that define the Hamiltonian cycle
@@ -189,12 +326,8 @@ fn generate_tour_numbers(
 
         dir = next_dir;
 
-        match next_dir {
-            Direction::Right => x += 1,
-            Direction::Left => x -= 1,
-            Direction::Down => y += 1,
-            Direction::Up => y -= 1,
-        }
+        let (dx, dy) = next_dir.delta();
+        (x, y) = (x + dx, y + dy);
 
         if number >= arena_size {
             break;
@@ -214,7 +347,7 @@ fn get_cycle_positions(
     // For each position in the arena, store its coordinates at its tour number index
     for x in 0..arena_width {
         for y in 0..arena_height {
-            let tour_number = get_path_number(tour_to_number, (x, y), arena_width) as usize;
+            let tour_number = get_path_number(tour_to_number, (x, y), arena_width);
             if tour_number < arena_size {
                 positions[tour_number] = (x as usize, y as usize);
             }
@@ -345,3 +478,159 @@ impl MazeNode {
         nodes[(x + y * maze_width) as usize].visited
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Checks the cycle visits every cell but the skipped one exactly once, a
+    // step at a time and back round to the start, and that the tour numbers
+    // give each cell's place along it
+    fn assert_valid_cycle(width: i32, height: i32, seed: u64) {
+        let (cycle, tour_numbers) =
+            generate_hamiltonian_cycle(width, height, &mut Rng::new(seed)).unwrap();
+        let (w, h) = (width as usize, height as usize);
+        let skipped = skipped_cell(width, height);
+
+        assert_eq!(cycle.len(), w * h - usize::from(skipped.is_some()));
+        for (i, &a) in cycle.iter().enumerate() {
+            let b = cycle[(i + 1) % cycle.len()];
+            assert_eq!(
+                a.0.abs_diff(b.0) + a.1.abs_diff(b.1),
+                1,
+                "{:?} -> {:?} on {}x{}, seed {}",
+                a,
+                b,
+                width,
+                height,
+                seed
+            );
+        }
+
+        let mut seen = vec![0; w * h];
+        for &(x, y) in &cycle {
+            seen[x + y * w] += 1;
+        }
+        for y in 0..h {
+            for x in 0..w {
+                let expected = if skipped == Some((x, y)) { 0 } else { 1 };
+                assert_eq!(
+                    seen[x + y * w],
+                    expected,
+                    "({}, {}) on {}x{}",
+                    x,
+                    y,
+                    width,
+                    height
+                );
+            }
+        }
+
+        assert_eq!(tour_numbers.len(), w * h);
+        for (i, &(x, y)) in cycle.iter().enumerate() {
+            assert_eq!(tour_numbers[x + y * w], i);
+        }
+        if let Some((x, y)) = skipped {
+            assert_eq!(tour_numbers[x + y * w], NOT_ON_CYCLE);
+        }
+    }
+
+    // The recursive walk the maze was built with before it kept its own stack
+    fn recursive_maze_paths(
+        nodes: &mut [MazeNode],
+        rng: &mut Rng,
+        from: (i32, i32),
+        cur: (i32, i32),
+        maze_width: i32,
+        maze_height: i32,
+    ) {
+        if !enter_maze_node(nodes, from, cur, maze_width, maze_height) {
+            return;
+        }
+        let (x, y) = cur;
+
+        for _ in 0..2 {
+            let target = match rng.gen_range(0, 4) {
+                0 => (x - 1, y),
+                1 => (x + 1, y),
+                2 => (x, y - 1),
+                _ => (x, y + 1),
+            };
+            recursive_maze_paths(nodes, rng, cur, target, maze_width, maze_height);
+        }
+
+        for target in [(x - 1, y), (x + 1, y), (x, y + 1), (x, y - 1)] {
+            recursive_maze_paths(nodes, rng, cur, target, maze_width, maze_height);
+        }
+    }
+
+    #[test]
+    fn maze_matches_the_recursive_walk() {
+        let unvisited = MazeNode {
+            visited: false,
+            can_go_right: false,
+            can_go_down: false,
+        };
+        let walls = |nodes: &[MazeNode]| -> Vec<(bool, bool, bool)> {
+            nodes
+                .iter()
+                .map(|node| (node.visited, node.can_go_right, node.can_go_down))
+                .collect()
+        };
+
+        for (maze_width, maze_height) in [(1, 1), (1, 5), (4, 3), (10, 10), (25, 15)] {
+            for seed in 0..10 {
+                let size = (maze_width * maze_height) as usize;
+                let (mut nodes, mut rng) = (vec![unvisited.clone(); size], Rng::new(seed));
+                generate_maze_paths(&mut nodes, &mut rng, maze_width, maze_height);
+
+                let (mut expected, mut expected_rng) =
+                    (vec![unvisited.clone(); size], Rng::new(seed));
+                recursive_maze_paths(
+                    &mut expected,
+                    &mut expected_rng,
+                    (-1, -1),
+                    (0, 0),
+                    maze_width,
+                    maze_height,
+                );
+
+                assert_eq!(walls(&nodes), walls(&expected));
+                // Both drew the same numbers, so everything after matches too
+                assert_eq!(rng.next_u32(), expected_rng.next_u32());
+            }
+        }
+    }
+
+    #[test]
+    fn large_board_does_not_overflow_the_stack() {
+        assert_valid_cycle(400, 400, 0);
+    }
+
+    #[test]
+    fn odd_width_is_spliced_in() {
+        for seed in 0..5 {
+            assert_valid_cycle(7, 6, seed);
+            assert_valid_cycle(9, 4, seed);
+            assert_valid_cycle(3, 2, seed);
+        }
+    }
+
+    #[test]
+    fn odd_height_is_spliced_in() {
+        for seed in 0..5 {
+            assert_valid_cycle(6, 7, seed);
+            assert_valid_cycle(4, 9, seed);
+            assert_valid_cycle(2, 3, seed);
+        }
+    }
+
+    #[test]
+    fn both_odd_skips_one_corner() {
+        for seed in 0..5 {
+            assert_valid_cycle(7, 7, seed);
+            assert_valid_cycle(5, 9, seed);
+            assert_valid_cycle(3, 3, seed);
+        }
+    }
+}
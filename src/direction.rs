@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+/// One of the four ways the snake can move. Moves are applied as `delta()`
+/// steps; this is how they are named in code, params files and replays.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Right,
+    Left,
+    Down,
+    Up,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Right,
+        Direction::Left,
+        Direction::Down,
+        Direction::Up,
+    ];
+
+    pub fn delta(self) -> (i32, i32) {
+        match self {
+            Direction::Right => (1, 0),
+            Direction::Left => (-1, 0),
+            Direction::Down => (0, 1),
+            Direction::Up => (0, -1),
+        }
+    }
+
+    pub fn from_delta(delta: (i32, i32)) -> Option<Direction> {
+        Self::ALL
+            .into_iter()
+            .find(|direction| direction.delta() == delta)
+    }
+
+    pub fn letter(self) -> char {
+        match self {
+            Direction::Right => 'R',
+            Direction::Left => 'L',
+            Direction::Down => 'D',
+            Direction::Up => 'U',
+        }
+    }
+
+    pub fn from_letter(letter: char) -> Option<Direction> {
+        Self::ALL
+            .into_iter()
+            .find(|direction| direction.letter() == letter)
+    }
+}
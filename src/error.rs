@@ -0,0 +1,92 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Reasons a game cannot be set up with the requested configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnakeError {
+    /// The board is too small to hold a cycle.
+    BoardTooSmall { width: i32, height: i32 },
+    /// The snake needs at least one segment.
+    EmptySnake,
+    /// Part of the snake would be placed off the board.
+    SnakeOutOfBounds {
+        x: usize,
+        y: usize,
+        length: usize,
+        width: usize,
+        height: usize,
+    },
+    /// The head would start on the cell the cycle leaves out of a board with
+    /// two odd sides, where no bot can follow the cycle from.
+    SpawnOffCycle { x: usize, y: usize },
+    /// The snake covers every free cell, leaving nowhere for an apple.
+    NoRoomForApple,
+}
+
+impl fmt::Display for SnakeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnakeError::BoardTooSmall { width, height } => write!(
+                f,
+                "a {}x{} board is too small, width and height must be at least 2",
+                width, height
+            ),
+            SnakeError::EmptySnake => write!(f, "the snake must be at least 1 segment long"),
+            SnakeError::SnakeOutOfBounds {
+                x,
+                y,
+                length,
+                width,
+                height,
+            } => write!(
+                f,
+                "a snake of length {} with its head at ({}, {}) does not fit on a {}x{} board \
+                 (it extends to the left of the head)",
+                length, x, y, width, height
+            ),
+            SnakeError::SpawnOffCycle { x, y } => write!(
+                f,
+                "the head cannot start at ({}, {}), the one cell left off the cycle when both \
+                 sides of the board are odd",
+                x, y
+            ),
+            SnakeError::NoRoomForApple => {
+                write!(f, "the snake fills the board, leaving no room for an apple")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnakeError {}
+
+/// Reasons a params, replay or save file could not be used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileError {
+    /// The file could not be read.
+    Read { path: PathBuf, message: String },
+    /// The file could not be written.
+    Write { path: PathBuf, message: String },
+    /// The file is not in the expected format, or has fields of the wrong type.
+    Parse { path: PathBuf, message: String },
+    /// The file parsed but holds values that cannot be used.
+    Invalid { path: PathBuf, message: String },
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileError::Read { path, message } => {
+                write!(f, "could not read {}: {}", path.display(), message)
+            }
+            FileError::Write { path, message } => {
+                write!(f, "could not write {}: {}", path.display(), message)
+            }
+            FileError::Parse { path, message } => {
+                write!(f, "could not parse {}: {}", path.display(), message)
+            }
+            FileError::Invalid { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for FileError {}
@@ -0,0 +1,63 @@
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::FileError;
+
+/// Writes `value` as JSON to `dir/file_name`, creating `dir` if needed, and
+/// returns the path written.
+pub fn write_json<T: Serialize>(
+    dir: &str,
+    file_name: &str,
+    value: &T,
+) -> Result<PathBuf, FileError> {
+    let path = Path::new(dir).join(file_name);
+    let write_error = |message: String| FileError::Write {
+        path: path.clone(),
+        message,
+    };
+
+    std::fs::create_dir_all(dir).map_err(|err| write_error(err.to_string()))?;
+    let json = serde_json::to_string(value).map_err(|err| write_error(err.to_string()))?;
+    std::fs::write(&path, json).map_err(|err| write_error(err.to_string()))?;
+    Ok(path)
+}
+
+/// Reads a JSON file written by `write_json`.
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, FileError> {
+    let text = std::fs::read_to_string(path).map_err(|err| FileError::Read {
+        path: path.to_path_buf(),
+        message: err.to_string(),
+    })?;
+    serde_json::from_str(&text).map_err(|err| FileError::Parse {
+        path: path.to_path_buf(),
+        message: err.to_string(),
+    })
+}
+
+/// Reads a TOML file into `T`.
+pub fn read_toml<T: DeserializeOwned>(path: &Path) -> Result<T, FileError> {
+    let text = std::fs::read_to_string(path).map_err(|err| FileError::Read {
+        path: path.to_path_buf(),
+        message: err.to_string(),
+    })?;
+    toml::from_str(&text).map_err(|err| FileError::Parse {
+        path: path.to_path_buf(),
+        message: err.to_string(),
+    })
+}
+
+/// Writes `value` as TOML to `path`, creating its directory if needed.
+pub fn write_toml<T: Serialize>(path: &Path, value: &T) -> Result<(), FileError> {
+    let write_error = |message: String| FileError::Write {
+        path: path.to_path_buf(),
+        message,
+    };
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|err| write_error(err.to_string()))?;
+    }
+    let text = toml::to_string(value).map_err(|err| write_error(err.to_string()))?;
+    std::fs::write(path, text).map_err(|err| write_error(err.to_string()))
+}
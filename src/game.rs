@@ -1,116 +1,641 @@
+use std::any::Any;
+use std::collections::VecDeque;
+
 use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::direction::Direction;
+use crate::error::{FileError, SnakeError};
+use crate::input::DirectionQueue;
+use crate::options::{check_board_size, GameConfig};
+use crate::overlay::{decision_summary, draw_decision, draw_markers, CycleOverlay, Overlay};
+use crate::params::BotParams;
+use crate::render::{draw_snake, CELL_GAP, CELL_SIZE};
+use crate::replay::{Playback, Replay};
+use crate::save::SavedGame;
+use crate::settings::Settings;
+use crate::sim::{Outcome, Simulation, Undo};
+use crate::snake::{DeathCause, Spawn};
+use crate::sprites::SpriteAtlas;
+use crate::strategy::{Decision, Strategy, StrategyKind};
+use crate::theme::Theme;
+
+pub const SNAKE_SPEED: f32 = 10.;
+
+const ARROW_KEYS: [(KeyCode, Direction); 8] = [
+    (KeyCode::Up, Direction::Up),
+    (KeyCode::W, Direction::Up),
+    (KeyCode::Down, Direction::Down),
+    (KeyCode::S, Direction::Down),
+    (KeyCode::Left, Direction::Left),
+    (KeyCode::A, Direction::Left),
+    (KeyCode::Right, Direction::Right),
+    (KeyCode::D, Direction::Right),
+];
+
+// Who steers the snake
+#[derive(PartialEq, Clone, Copy)]
+pub enum ControlMode {
+    Bot,
+    Human,
+}
+
+/// The screen the game is on. Each state has its own update, render and input
+/// handling, and input moves the game between them.
+pub enum GameState {
+    MainMenu,
+    Playing,
+    Paused,
+    Won,
+    Died(DeathCause),
+    Replay(Playback),
+}
+
+/// What happens to the board when the window is resized.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeMode {
+    /// Keep the board and scale its cells to fit the window.
+    Scale,
+    /// Start a new game on a board with as many cells as fit.
+    Regenerate,
+}
+
+impl ResizeMode {
+    fn name(self) -> &'static str {
+        match self {
+            ResizeMode::Scale => "scale",
+            ResizeMode::Regenerate => "regenerate",
+        }
+    }
+}
+
+// The main menu entries, top to bottom
+#[derive(PartialEq, Clone, Copy)]
+enum MenuItem {
+    WatchBot,
+    PlayYourself,
+    ChooseBot,
+    Quit,
+}
 
-use crate::bot::generate_hamiltonian_cycle;
-use crate::snake::{Snake, CELL_GAP};
-use crate::snake::{CELL_SIZE, SNAKE_SPEED};
+const MENU_ITEMS: [MenuItem; 4] = [
+    MenuItem::WatchBot,
+    MenuItem::PlayYourself,
+    MenuItem::ChooseBot,
+    MenuItem::Quit,
+];
 
 pub struct Game {
-    score: u32,
-    is_over: bool,
-    snake: Snake,
+    state: GameState,
+    sim: Simulation,
+    fixed_seed: Option<u64>,
+    // The board every new game is set up on, in cells
+    board: (usize, usize),
+    spawn: Spawn,
+    // Pixels per cell the window was opened with, for fitting a new board to
+    // a resized window
+    cell_size: f32,
+    mode: ControlMode,
+    strategy_kind: StrategyKind,
+    strategy: Box<dyn Strategy>,
+    input: DirectionQueue,
     step_timer: f32,
-    open_cells: Vec<(usize, usize)>,
-    apple: (usize, usize),
-    cycle: Vec<(usize, usize)>,
-    tour_numbers: Vec<usize>,
+    // Seconds spent playing this game, shown when it is won
+    play_time: f32,
     draw_cycle: bool,
+    overlay: Overlay,
+    show_decision: bool,
+    resize_mode: ResizeMode,
+    theme: Theme,
+    use_sprites: bool,
+    // Kept when it fails to load, to say why when sprites are asked for
+    sprite_atlas: Result<SpriteAtlas, FileError>,
+    // The window size last frame, to spot resizes
+    screen_size: (f32, f32),
+    // Seconds the window has kept its size since it last changed, while a
+    // regenerated board waits for the resize to finish
+    resize_timer: Option<f32>,
     speed_multiplier: f32,
+    params: BotParams,
+    show_params: bool,
+    // Index into MENU_ITEMS of the highlighted entry
+    menu_selection: usize,
+    // A one-line note for the player, like where a replay was saved
+    status: Option<String>,
+    // The most recent moves, newest at the back, for stepping backwards
+    history: VecDeque<HistoryEntry>,
+    // Why the bot made the last move, for the decision overlay
+    decision: Option<Decision>,
+    // The settings file, updated as the controls panel changes and written
+    // back once the mouse is let go
+    settings: Settings,
+    settings_changed: bool,
 }
 
-impl Game {
-    pub fn new() -> Self {
-        let screen_width = screen_width();
-        let screen_height = screen_height();
+// The game just before one move, enough to undo it
+struct HistoryEntry {
+    undo: Undo,
+    // Bots that reshape their cycle have to be rewound along with the board
+    strategy_state: Option<Box<dyn Any>>,
+    decision: Option<Decision>,
+}
 
-        let snake = Snake::spawn_on_map(5, 5, 4);
+// How many moves can be stepped back through
+const HISTORY_LEN: usize = 500;
 
-        let grid_width = screen_width / CELL_SIZE;
-        let grid_height = screen_height / CELL_SIZE;
+// How long the window has to keep its size before the board is regenerated,
+// so dragging its edge does not start a new game on every frame
+const RESIZE_SETTLE_SECS: f32 = 0.3;
 
-        let mut open_cells = Vec::new();
-        for x in 0..(grid_width as usize) {
-            for y in 0..(grid_height as usize) {
-                if !snake.segments.iter().any(|s| s.cur == (x, y)) {
-                    open_cells.push((x, y));
-                }
-            }
-        }
-        rand::srand(macroquad::miniquad::date::now() as _);
-        let apple_index = rand::gen_range(0, open_cells.len());
-        let apple = open_cells[apple_index];
+// How far Up and Down jump through a replay
+const REPLAY_SEEK_STEP: usize = 100;
 
-        let (cycle, tour_numbers) =
-            generate_hamiltonian_cycle(grid_width as i32, grid_height as i32);
+// The tunable params, in the order they appear in the tuning panel, with how
+// far one click on - or + moves them
+const PARAM_ROWS: [(&str, usize); 4] = [
+    ("Buffer:", 5),
+    ("Food value:", 1),
+    ("Penalty:", 1),
+    ("Empty factor:", 1),
+];
 
+impl Game {
+    /// Opens on the main menu, ready to start a game set up as `config` says.
+    /// A fixed seed is reused on every restart, otherwise the clock picks one.
+    pub fn new(config: GameConfig) -> Result<Self, SnakeError> {
+        let sim = Self::new_simulation((config.width, config.height), config.spawn, config.seed)?;
+        Ok(Self::with_config(GameState::MainMenu, sim, config))
+    }
+
+    // Everything set up as `config` says, showing `sim` in `state`
+    fn with_config(state: GameState, sim: Simulation, config: GameConfig) -> Self {
+        let sprite_atlas = SpriteAtlas::load(&config.assets);
         Game {
-            score: 0,
-            is_over: false,
-            snake,
+            state,
+            sim,
+            fixed_seed: config.seed,
+            board: (config.width, config.height),
+            spawn: config.spawn,
+            cell_size: config.cell_size,
+            mode: ControlMode::Bot,
+            strategy_kind: config.strategy,
+            strategy: config.strategy.build(&config.params),
+            input: DirectionQueue::new(),
             step_timer: 0.0,
-            open_cells,
-            apple,
-            cycle,
-            tour_numbers,
-            draw_cycle: false,
-            speed_multiplier: 5.0,
+            play_time: 0.0,
+            draw_cycle: config.settings.show_cycle,
+            overlay: config.settings.overlay,
+            show_decision: config.settings.show_decision,
+            resize_mode: config.settings.resize,
+            theme: config.settings.theme(),
+            use_sprites: config.settings.sprites,
+            screen_size: (screen_width(), screen_height()),
+            resize_timer: None,
+            speed_multiplier: config.speed,
+            params: config.params,
+            show_params: false,
+            menu_selection: 0,
+            status: Self::sprite_status(config.settings.sprites, &sprite_atlas),
+            sprite_atlas,
+            history: VecDeque::new(),
+            decision: None,
+            settings: config.settings,
+            settings_changed: false,
+        }
+    }
+
+    /// Opens a replay for playback instead of starting a new game. Games
+    /// started from the menu afterwards are set up as `config` says.
+    pub fn from_replay(replay: Replay, config: GameConfig) -> Result<Self, SnakeError> {
+        let strategy_kind =
+            StrategyKind::from_name(&replay.strategy).unwrap_or(StrategyKind::HamiltonianShortcut);
+        let strategy = strategy_kind.build(&config.params);
+        let (playback, sim) = Playback::new(replay)?;
+        Ok(Game {
+            strategy_kind,
+            strategy,
+            ..Self::with_config(GameState::Replay(playback), sim, config)
+        })
+    }
+
+    /// Carries on a game from a save file exactly where it left off. It opens
+    /// paused, so the position can be looked over first. Restarts keep the
+    /// saved game's board and start.
+    pub fn from_saved(saved: SavedGame, config: GameConfig) -> Self {
+        let strategy_kind = saved.strategy_kind();
+        // `SavedGame::load` has already checked the strategy state restores
+        let strategy = saved
+            .restore_strategy()
+            .unwrap_or_else(|_| strategy_kind.build(&saved.params));
+        let state = match saved.sim.outcome() {
+            Some(Outcome::Won) => GameState::Won,
+            Some(Outcome::Died(cause)) => GameState::Died(cause),
+            None => GameState::Paused,
+        };
+        Game {
+            fixed_seed: saved.fixed_seed,
+            board: (saved.sim.width(), saved.sim.height()),
+            spawn: saved.sim.spawn(),
+            mode: if saved.human {
+                ControlMode::Human
+            } else {
+                ControlMode::Bot
+            },
+            strategy_kind,
+            strategy,
+            step_timer: saved.step_timer,
+            play_time: saved.play_time,
+            draw_cycle: saved.draw_cycle,
+            speed_multiplier: saved.speed_multiplier,
+            params: saved.params,
+            ..Self::with_config(state, saved.sim, config)
         }
     }
 
+    fn new_simulation(
+        (width, height): (usize, usize),
+        spawn: Spawn,
+        seed: Option<u64>,
+    ) -> Result<Simulation, SnakeError> {
+        let seed = seed.unwrap_or_else(|| macroquad::miniquad::date::now() as u64);
+        Simulation::with_spawn(width, height, seed, spawn)
+    }
+
     pub fn update(&mut self, delta_time: f32) {
-        if self.is_over {
-            return;
+        self.update_screen_size(delta_time);
+        self.save_settings();
+
+        match &mut self.state {
+            GameState::MainMenu | GameState::Won | GameState::Died(_) => {}
+            GameState::Playing => {
+                self.update_playing(delta_time);
+                self.update_controls();
+                if self.show_params {
+                    self.update_params_panel();
+                }
+            }
+            // The controls stay live so speed and overlays can be set up
+            // before carrying on
+            GameState::Paused => {
+                self.update_controls();
+                if self.show_params {
+                    self.update_params_panel();
+                }
+            }
+            GameState::Replay(playback) => {
+                self.step_timer += delta_time;
+                if !playback.paused && self.step_timer >= 1. / (SNAKE_SPEED * self.speed_multiplier)
+                {
+                    playback.advance(&mut self.sim);
+                    self.step_timer = 0.;
+                }
+                self.update_controls();
+                self.update_progress_bar();
+            }
         }
+    }
 
+    fn update_playing(&mut self, delta_time: f32) {
         self.step_timer += delta_time;
+        self.play_time += delta_time;
 
         if self.step_timer >= 1. / (SNAKE_SPEED * self.speed_multiplier) {
-            let (head, tail) = self.snake.step(&self.cycle, self.apple, &self.tour_numbers);
-            // remove head from open_cells
-            self.open_cells.retain(|&cell| cell != head);
-            // add tail to open_cells
-
-            if self.snake.is_eating(self.apple) {
-                self.score += 1;
-                self.snake.grow();
-
-                // Spawn a new apple
-                if !self.open_cells.is_empty() {
-                    self.apple = self.spawn_apple(&self.open_cells);
-                } else {
-                    // No more open cells, game over
-                    self.is_over = true;
+            self.step_once();
+            self.step_timer = 0.;
+        }
+    }
+
+    // Move the snake exactly one cell, remembering the game before it so the
+    // move can be undone
+    fn step_once(&mut self) {
+        if self.sim.is_over() {
+            return;
+        }
+        let strategy_state = self.strategy.checkpoint();
+        let (direction, decision) = match self.mode {
+            ControlMode::Bot => self.strategy.decide(&self.sim.view()),
+            ControlMode::Human => (self.input.next(self.sim.snake().direction()), None),
+        };
+        let Some(undo) = self.sim.step_towards(direction) else {
+            return;
+        };
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(HistoryEntry {
+            undo,
+            strategy_state,
+            decision: std::mem::replace(&mut self.decision, decision),
+        });
+
+        match self.sim.outcome() {
+            Some(Outcome::Won) => self.state = GameState::Won,
+            Some(Outcome::Died(cause)) => self.state = GameState::Died(cause),
+            None => {}
+        }
+    }
+
+    // Undo the last move, leaving the game paused there. Does nothing once the
+    // history runs out
+    fn step_back(&mut self) {
+        let Some(entry) = self.history.pop_back() else {
+            return;
+        };
+        self.sim.undo(entry.undo);
+        if let Some(state) = entry.strategy_state {
+            self.strategy.rewind(state);
+        }
+        self.decision = entry.decision;
+        self.input.clear();
+        self.step_timer = 0.0;
+        self.state = GameState::Paused;
+    }
+
+    pub fn render(&self) {
+        clear_background(self.theme.background);
+        // Shared by the board and the HUD, so the cycle is only measured once
+        let overlay = (self.overlay != Overlay::Off).then(|| self.cycle_overlay());
+        let overlay = overlay.as_ref();
+
+        match &self.state {
+            GameState::MainMenu => self.draw_main_menu(),
+            GameState::Playing => {
+                self.draw_board(overlay);
+                self.draw_hud(overlay);
+            }
+            GameState::Paused => {
+                self.draw_board(overlay);
+                self.draw_hud(overlay);
+                self.draw_paused();
+            }
+            GameState::Won | GameState::Died(_) => {
+                let outcome = match self.state {
+                    GameState::Died(cause) => Outcome::Died(cause),
+                    _ => Outcome::Won,
+                };
+                self.draw_board(overlay);
+                self.draw_score(overlay);
+                self.draw_game_over(
+                    outcome,
+                    "Press Enter to restart, R to save a replay or Escape for the menu.",
+                );
+            }
+            GameState::Replay(playback) => {
+                self.draw_board(overlay);
+                self.draw_hud(overlay);
+                if let Some(outcome) = self.sim.outcome() {
+                    self.draw_game_over(
+                        outcome,
+                        "Press Enter to watch again or Escape for the menu.",
+                    );
                 }
-            } else {
-                self.open_cells.push(tail);
+                self.draw_progress_bar(playback);
+            }
+        }
+    }
+
+    // Refit the board after the window changes size. Scaling needs nothing
+    // here, the board camera follows the window on its own
+    fn update_screen_size(&mut self, delta_time: f32) {
+        let screen_size = (screen_width(), screen_height());
+        if screen_size != self.screen_size {
+            self.screen_size = screen_size;
+            // A replay has to stay on the board it was recorded on
+            if self.resize_mode == ResizeMode::Regenerate && !self.is_replay() {
+                self.resize_timer = Some(0.0);
             }
+            return;
+        }
 
-            self.check_for_death();
-            self.step_timer = 0.;
+        let Some(timer) = &mut self.resize_timer else {
+            return;
+        };
+        *timer += delta_time;
+        if *timer < RESIZE_SETTLE_SECS {
+            return;
+        }
+        self.resize_timer = None;
+        // Switched back to scaling while waiting
+        if self.resize_mode != ResizeMode::Regenerate {
+            return;
         }
 
-        // Update UI controls
-        self.update_controls();
+        let board = (
+            (screen_size.0 / self.cell_size) as usize,
+            (screen_size.1 / self.cell_size) as usize,
+        );
+        if board == self.board {
+            return;
+        }
+        // Keep the old board if the new one is too large or the snake's start
+        // does not fit on it
+        let sim = match check_board_size(board.0, board.1).and_then(|()| {
+            Self::new_simulation(board, self.spawn, self.fixed_seed).map_err(|err| err.to_string())
+        }) {
+            Ok(sim) => sim,
+            Err(err) => {
+                self.status = Some(format!("Kept the board: {}", err));
+                return;
+            }
+        };
+        self.board = board;
+        self.change_settings(|settings| {
+            settings.width = Some(board.0);
+            settings.height = Some(board.1);
+        });
+        self.reset_to(sim);
+        if matches!(self.state, GameState::Won | GameState::Died(_)) {
+            self.state = GameState::Playing;
+        }
     }
 
-    pub fn render(&self) {
+    fn board_camera(&self) -> Camera2D {
+        board_camera(
+            (self.sim.width(), self.sim.height()),
+            (screen_width(), screen_height()),
+        )
+    }
+
+    fn draw_board(&self, overlay: Option<&CycleOverlay>) {
+        set_camera(&self.board_camera());
+        self.draw_board_cells(overlay);
+        // The HUD is laid out in window pixels
+        set_default_camera();
+    }
+
+    fn draw_board_cells(&self, overlay: Option<&CycleOverlay>) {
+        self.draw_grid();
+        if let (Overlay::Heatmap, Some(overlay)) = (self.overlay, overlay) {
+            overlay.draw_heatmap(&self.theme);
+        }
         if self.draw_cycle {
             self.draw_cycle_path();
         }
-        self.snake.draw(self.step_timer);
+        self.draw_skipped_cell();
+        let ratio = (self.step_timer * SNAKE_SPEED).min(1.0);
+        match (self.use_sprites, &self.sprite_atlas) {
+            (true, Ok(atlas)) => atlas.draw_snake(self.sim.snake(), ratio),
+            _ => draw_snake(self.sim.snake(), ratio, &self.theme),
+        }
         self.draw_apple();
-        self.draw_score();
+        if let Some(overlay) = overlay {
+            if self.overlay == Overlay::TourNumbers {
+                overlay.draw_tour_numbers(&self.theme);
+            }
+            let (head, tail) = self.head_and_tail();
+            draw_markers(head, tail, self.sim.apple(), &self.theme);
+        }
+        if let (true, Some(decision)) = (self.show_decision, &self.decision) {
+            draw_decision(decision, &self.theme);
+        }
+    }
+
+    // The cycle the bot is following, measured from the snake's head
+    fn cycle_overlay(&self) -> CycleOverlay {
+        let cycle = self.strategy.cycle().unwrap_or(self.sim.cycle());
+        CycleOverlay::new(
+            cycle,
+            self.sim.width(),
+            self.sim.height(),
+            self.head_and_tail().0,
+        )
+    }
+
+    fn head_and_tail(&self) -> ((usize, usize), (usize, usize)) {
+        let segments = &self.sim.snake().segments;
+        (segments[0].cur, segments[segments.len() - 1].cur)
+    }
+
+    fn draw_hud(&self, overlay: Option<&CycleOverlay>) {
+        self.draw_score(overlay);
         self.draw_controls();
-        if self.is_over {
-            self.draw_game_over();
+        if self.show_params {
+            self.draw_params_panel();
+        }
+    }
+
+    fn draw_main_menu(&self) {
+        let screen_width = screen_width();
+        let title = "Hamiltonian Snake";
+        let title_width = measure_text(title, None, 40, 1.0).width;
+        let top = screen_height() / 2.0 - 80.0;
+        draw_text(
+            title,
+            (screen_width - title_width) / 2.0,
+            top,
+            40.0,
+            self.theme.text,
+        );
+
+        for (i, item) in MENU_ITEMS.iter().enumerate() {
+            let label = match item {
+                MenuItem::WatchBot => "Watch the bot".to_owned(),
+                MenuItem::PlayYourself => "Play yourself".to_owned(),
+                MenuItem::ChooseBot => format!("Bot: < {} >", self.strategy_kind.name()),
+                MenuItem::Quit => "Quit".to_owned(),
+            };
+            let selected = i == self.menu_selection;
+            let label = if selected {
+                format!("> {}", label)
+            } else {
+                label
+            };
+            let label_width = measure_text(&label, None, 25, 1.0).width;
+            draw_text(
+                &label,
+                (screen_width - label_width) / 2.0,
+                top + 50.0 + i as f32 * 30.0,
+                25.0,
+                if selected {
+                    self.theme.text
+                } else {
+                    self.theme.control
+                },
+            );
         }
+
+        let hint = "Up/Down to choose, Enter to select";
+        let hint_width = measure_text(hint, None, 15, 1.0).width;
+        draw_text(
+            hint,
+            (screen_width - hint_width) / 2.0,
+            screen_height() - 30.0,
+            15.0,
+            self.theme.control,
+        );
+    }
+
+    fn draw_paused(&self) {
+        let text = "Paused: Space to resume, . / , to step, Escape for the menu";
+        let text_width = measure_text(text, None, 20, 1.0).width;
+        draw_text(
+            text,
+            (screen_width() - text_width) / 2.0,
+            screen_height() - 50.0,
+            20.0,
+            self.theme.text,
+        );
+    }
+
+    // Where the replay progress bar sits: along the bottom edge
+    fn progress_bar_rect() -> Rect {
+        Rect::new(10.0, screen_height() - 20.0, screen_width() - 20.0, 8.0)
+    }
+
+    fn draw_progress_bar(&self, playback: &Playback) {
+        let bar = Self::progress_bar_rect();
+        draw_rectangle(bar.x, bar.y, bar.w, bar.h, self.theme.control);
+        let played = playback.position() as f32 / playback.len().max(1) as f32;
+        draw_rectangle(bar.x, bar.y, bar.w * played, bar.h, self.theme.text);
+    }
+
+    // Click or drag on the progress bar to jump through the replay
+    fn update_progress_bar(&mut self) {
+        if !is_mouse_button_down(MouseButton::Left) {
+            return;
+        }
+        let bar = Self::progress_bar_rect();
+        let (mouse_x, mouse_y) = mouse_position();
+        if mouse_y < bar.y - 5.0 || mouse_y > bar.y + bar.h + 5.0 {
+            return;
+        }
+        if let GameState::Replay(playback) = &self.state {
+            let fraction = ((mouse_x - bar.x) / bar.w).clamp(0.0, 1.0);
+            let target = (fraction * playback.len() as f32).round() as usize;
+            self.seek_replay(target);
+        }
+    }
+
+    fn seek_replay(&mut self, target: usize) {
+        if let GameState::Replay(playback) = &mut self.state {
+            if let Err(err) = playback.seek(&mut self.sim, target) {
+                self.status = Some(format!("Could not rewind the replay: {}", err));
+            }
+            self.step_timer = 0.;
+        }
+    }
+
+    // Save the game so far to the replays directory
+    fn save_replay(&mut self) {
+        let replay = Replay::record(
+            &self.sim,
+            self.strategy_kind,
+            self.mode == ControlMode::Human,
+        );
+        self.status = Some(match replay.save() {
+            Ok(path) => format!("Saved replay to {}", path.display()),
+            Err(err) => format!("Could not save replay: {}", err),
+        });
     }
 
     fn draw_cycle_path(&self) {
-        // Draw a thin line connecting all points in the cycle
-        for i in 0..self.cycle.len() {
-            let (x1, y1) = self.cycle[i];
+        // Draw a thin line connecting all points in the cycle the bot is following
+        let cycle = self.strategy.cycle().unwrap_or(self.sim.cycle());
+        for i in 0..cycle.len() {
+            let (x1, y1) = cycle[i];
             // Get the next point in the cycle (wrapping around to the first point)
-            let (x2, y2) = self.cycle[(i + 1) % self.cycle.len()];
+            let (x2, y2) = cycle[(i + 1) % cycle.len()];
 
             // Calculate center coordinates of each cell
             let start_x = x1 as f32 * CELL_SIZE + CELL_SIZE / 2.0;
@@ -119,7 +644,39 @@ impl Game {
             let end_y = y2 as f32 * CELL_SIZE + CELL_SIZE / 2.0;
 
             // Draw a thin line between the centers
-            draw_line(start_x, start_y, end_x, end_y, 1.0, RED);
+            draw_line(start_x, start_y, end_x, end_y, 1.0, self.theme.cycle);
+        }
+    }
+
+    // Shade the cell the cycle leaves out on boards with two odd sides
+    fn draw_skipped_cell(&self) {
+        if let Some((x, y)) = self.sim.skipped_cell() {
+            draw_rectangle(
+                x as f32 * CELL_SIZE + CELL_GAP / 2.0,
+                y as f32 * CELL_SIZE + CELL_GAP / 2.0,
+                CELL_SIZE - CELL_GAP,
+                CELL_SIZE - CELL_GAP,
+                self.theme.skipped_cell,
+            );
+        }
+    }
+
+    // Outline every cell, for themes that have a grid
+    fn draw_grid(&self) {
+        if self.theme.grid.a == 0.0 {
+            return;
+        }
+        for y in 0..self.sim.height() {
+            for x in 0..self.sim.width() {
+                draw_rectangle_lines(
+                    x as f32 * CELL_SIZE,
+                    y as f32 * CELL_SIZE,
+                    CELL_SIZE,
+                    CELL_SIZE,
+                    1.0,
+                    self.theme.grid,
+                );
+            }
         }
     }
 
@@ -127,7 +684,7 @@ impl Game {
         // Position controls in top right corner
         let screen_width = screen_width();
         let control_y = 10.0;
-        let control_height = 50.0;
+        let control_height = 200.0;
         let control_width = 200.0;
         let control_x = screen_width - control_width - 10.0;
 
@@ -136,12 +693,12 @@ impl Game {
             control_y,
             control_width,
             control_height,
-            Color::new(0.0, 0.0, 0.0, 0.5),
+            self.theme.panel,
         );
 
         // Draw speed control
         let speed_y = control_y + 10.0;
-        draw_text("Speed:", control_x + 10.0, speed_y, 15.0, WHITE);
+        draw_text("Speed:", control_x + 10.0, speed_y, 15.0, self.theme.text);
 
         // Speed slider background
         let slider_x = control_x + 70.0;
@@ -152,7 +709,7 @@ impl Game {
             speed_y - slider_height,
             slider_width,
             slider_height,
-            GRAY,
+            self.theme.control,
         );
 
         // Speed slider knob position - map from 0.5-100.0 to 0.0-1.0
@@ -163,7 +720,7 @@ impl Game {
             knob_x,
             speed_y - slider_height / 2.0,
             knob_size / 2.0,
-            WHITE,
+            self.theme.text,
         );
 
         // Speed value
@@ -172,12 +729,18 @@ impl Game {
             slider_x + slider_width + 10.0,
             speed_y,
             15.0,
-            WHITE,
+            self.theme.text,
         );
 
         // Draw cycle visibility checkbox
         let cycle_y = speed_y + 25.0;
-        draw_text("Show Cycle:", control_x + 10.0, cycle_y, 15.0, WHITE);
+        draw_text(
+            "Show Cycle:",
+            control_x + 10.0,
+            cycle_y,
+            15.0,
+            self.theme.text,
+        );
 
         // Checkbox
         let checkbox_x = slider_x;
@@ -187,27 +750,124 @@ impl Game {
             cycle_y - checkbox_size,
             checkbox_size,
             checkbox_size,
-            GRAY,
+            self.theme.control,
         );
 
         // Check mark if enabled
         if self.draw_cycle {
-            draw_line(
-                checkbox_x + 2.0,
-                cycle_y - checkbox_size / 2.0,
-                checkbox_x + checkbox_size / 2.0,
-                cycle_y - 2.0,
-                2.0,
-                WHITE,
-            );
-            draw_line(
-                checkbox_x + checkbox_size / 2.0,
-                cycle_y - 2.0,
-                checkbox_x + checkbox_size - 2.0,
-                cycle_y - checkbox_size + 2.0,
-                2.0,
-                WHITE,
-            );
+            draw_check_mark(checkbox_x, cycle_y, checkbox_size, self.theme.text);
+        }
+
+        // Draw human control checkbox
+        let human_y = cycle_y + 25.0;
+        draw_text("Human:", control_x + 10.0, human_y, 15.0, self.theme.text);
+        draw_rectangle(
+            checkbox_x,
+            human_y - checkbox_size,
+            checkbox_size,
+            checkbox_size,
+            self.theme.control,
+        );
+        if self.mode == ControlMode::Human {
+            draw_check_mark(checkbox_x, human_y, checkbox_size, self.theme.text);
+        }
+
+        // Draw strategy selector, click to cycle through the built-in bots
+        let strategy_y = human_y + 25.0;
+        draw_text("Bot:", control_x + 10.0, strategy_y, 15.0, self.theme.text);
+        draw_rectangle(
+            slider_x,
+            strategy_y - checkbox_size,
+            slider_width,
+            checkbox_size + 3.0,
+            self.theme.control,
+        );
+        draw_text(
+            self.strategy_kind.name(),
+            slider_x + 5.0,
+            strategy_y,
+            15.0,
+            self.theme.text,
+        );
+
+        // Draw overlay selector, click to cycle through the overlays
+        let overlay_y = strategy_y + 25.0;
+        draw_text(
+            "Overlay:",
+            control_x + 10.0,
+            overlay_y,
+            15.0,
+            self.theme.text,
+        );
+        draw_rectangle(
+            slider_x,
+            overlay_y - checkbox_size,
+            slider_width,
+            checkbox_size + 3.0,
+            self.theme.control,
+        );
+        draw_text(
+            self.overlay.name(),
+            slider_x + 5.0,
+            overlay_y,
+            15.0,
+            self.theme.text,
+        );
+
+        // Draw resize selector, click to switch what a window resize does
+        let resize_y = overlay_y + 25.0;
+        draw_text("Resize:", control_x + 10.0, resize_y, 15.0, self.theme.text);
+        draw_rectangle(
+            slider_x,
+            resize_y - checkbox_size,
+            slider_width,
+            checkbox_size + 3.0,
+            self.theme.control,
+        );
+        draw_text(
+            self.resize_mode.name(),
+            slider_x + 5.0,
+            resize_y,
+            15.0,
+            self.theme.text,
+        );
+
+        // Draw theme selector, click to cycle through the themes
+        let theme_y = resize_y + 25.0;
+        draw_text("Theme:", control_x + 10.0, theme_y, 15.0, self.theme.text);
+        draw_rectangle(
+            slider_x,
+            theme_y - checkbox_size,
+            slider_width,
+            checkbox_size + 3.0,
+            self.theme.control,
+        );
+        draw_text(
+            &self.settings.theme,
+            slider_x + 5.0,
+            theme_y,
+            15.0,
+            self.theme.text,
+        );
+
+        // Draw sprite renderer checkbox
+        let sprites_y = theme_y + 25.0;
+        draw_text(
+            "Sprites:",
+            control_x + 10.0,
+            sprites_y,
+            15.0,
+            self.theme.text,
+        );
+        draw_rectangle(
+            checkbox_x,
+            sprites_y - checkbox_size,
+            checkbox_size,
+            checkbox_size,
+            self.theme.control,
+        );
+        if self.use_sprites {
+            draw_check_mark(checkbox_x, sprites_y, checkbox_size, self.theme.text);
         }
     }
 
@@ -231,9 +891,11 @@ impl Game {
                 && mouse_pos.0 <= slider_x + slider_width
             {
                 let normalized_pos = (mouse_pos.0 - slider_x) / slider_width;
-                let clamped_pos = normalized_pos.max(0.0).min(1.0);
+                let clamped_pos = normalized_pos.clamp(0.0, 1.0);
                 // Map 0.0-1.0 to 0.5-100.0
                 self.speed_multiplier = 0.5 + (clamped_pos * 99.5);
+                let speed = self.speed_multiplier;
+                self.change_settings(|settings| settings.speed = speed);
             }
 
             let cycle_y = speed_y + 25.0;
@@ -247,19 +909,297 @@ impl Game {
                 && mouse_pos.0 <= checkbox_x + checkbox_size
             {
                 self.draw_cycle = !self.draw_cycle;
+                let show_cycle = self.draw_cycle;
+                self.change_settings(|settings| settings.show_cycle = show_cycle);
+            }
+
+            let human_y = cycle_y + 25.0;
+
+            if is_mouse_button_pressed(MouseButton::Left)
+                && mouse_pos.1 >= human_y - checkbox_size
+                && mouse_pos.1 <= human_y
+                && mouse_pos.0 >= checkbox_x
+                && mouse_pos.0 <= checkbox_x + checkbox_size
+            {
+                self.toggle_mode();
+            }
+
+            let strategy_y = human_y + 25.0;
+
+            if is_mouse_button_pressed(MouseButton::Left)
+                && mouse_pos.1 >= strategy_y - checkbox_size
+                && mouse_pos.1 <= strategy_y + 3.0
+                && mouse_pos.0 >= slider_x
+                && mouse_pos.0 <= slider_x + slider_width
+            {
+                self.set_strategy(self.strategy_kind.next());
+            }
+
+            let overlay_y = strategy_y + 25.0;
+
+            if is_mouse_button_pressed(MouseButton::Left)
+                && mouse_pos.1 >= overlay_y - checkbox_size
+                && mouse_pos.1 <= overlay_y + 3.0
+                && mouse_pos.0 >= slider_x
+                && mouse_pos.0 <= slider_x + slider_width
+            {
+                self.cycle_overlay_mode();
+            }
+
+            let resize_y = overlay_y + 25.0;
+
+            if is_mouse_button_pressed(MouseButton::Left)
+                && mouse_pos.1 >= resize_y - checkbox_size
+                && mouse_pos.1 <= resize_y + 3.0
+                && mouse_pos.0 >= slider_x
+                && mouse_pos.0 <= slider_x + slider_width
+            {
+                self.resize_mode = match self.resize_mode {
+                    ResizeMode::Scale => ResizeMode::Regenerate,
+                    ResizeMode::Regenerate => ResizeMode::Scale,
+                };
+                let resize = self.resize_mode;
+                self.change_settings(|settings| settings.resize = resize);
+            }
+
+            let theme_y = resize_y + 25.0;
+
+            if is_mouse_button_pressed(MouseButton::Left)
+                && mouse_pos.1 >= theme_y - checkbox_size
+                && mouse_pos.1 <= theme_y + 3.0
+                && mouse_pos.0 >= slider_x
+                && mouse_pos.0 <= slider_x + slider_width
+            {
+                self.next_theme();
+            }
+
+            let sprites_y = theme_y + 25.0;
+
+            if is_mouse_button_pressed(MouseButton::Left)
+                && mouse_pos.1 >= sprites_y - checkbox_size
+                && mouse_pos.1 <= sprites_y
+                && mouse_pos.0 >= checkbox_x
+                && mouse_pos.0 <= checkbox_x + checkbox_size
+            {
+                self.use_sprites = !self.use_sprites;
+                let sprites = self.use_sprites;
+                self.change_settings(|settings| settings.sprites = sprites);
+                self.status = Self::sprite_status(sprites, &self.sprite_atlas);
+            }
+        }
+    }
+
+    fn cycle_overlay_mode(&mut self) {
+        self.overlay = self.overlay.next();
+        let overlay = self.overlay;
+        self.change_settings(|settings| settings.overlay = overlay);
+    }
+
+    // Sprites fall back to shapes when the atlas did not load, and say why
+    fn sprite_status(
+        use_sprites: bool,
+        sprite_atlas: &Result<SpriteAtlas, FileError>,
+    ) -> Option<String> {
+        match (use_sprites, sprite_atlas) {
+            (true, Err(err)) => Some(format!("Drawing the snake as shapes: {}", err)),
+            _ => None,
+        }
+    }
+
+    // Switch to the theme after this one, built-in themes first, then the
+    // settings file's own
+    fn next_theme(&mut self) {
+        let names = Theme::names(&self.settings.themes);
+        let current = names
+            .iter()
+            .position(|name| *name == self.settings.theme)
+            .unwrap_or(0);
+        let name = names[(current + 1) % names.len()].clone();
+        self.theme = Theme::find(&name, &self.settings.themes).unwrap_or_default();
+        self.change_settings(|settings| settings.theme = name);
+    }
+
+    // Record a change made through the UI in the settings file. Only the
+    // changed setting is touched, so command-line options are not saved
+    fn change_settings(&mut self, change: impl FnOnce(&mut Settings)) {
+        change(&mut self.settings);
+        self.settings_changed = true;
+    }
+
+    // Written once the mouse is let go, rather than on every frame of a drag
+    fn save_settings(&mut self) {
+        if !self.settings_changed || is_mouse_button_down(MouseButton::Left) {
+            return;
+        }
+        self.settings_changed = false;
+        if let Err(err) = self.settings.save() {
+            self.status = Some(format!("Could not save settings: {}", err));
+        }
+    }
+
+    // Swap the bot mid-game; the new strategy starts from a clean state
+    fn set_strategy(&mut self, kind: StrategyKind) {
+        // A replay's moves are fixed, there is no bot to swap
+        if self.is_replay() {
+            return;
+        }
+        self.strategy_kind = kind;
+        self.change_settings(|settings| settings.strategy = kind.name().to_owned());
+        self.strategy = kind.build(&self.params);
+        // Earlier states were saved by the old bot and would not load into this one
+        self.history.clear();
+        self.decision = None;
+    }
+
+    // The tuning panel sits under the controls panel
+    fn params_panel_origin() -> (f32, f32) {
+        (screen_width() - 200.0 - 10.0, 220.0)
+    }
+
+    fn draw_params_panel(&self) {
+        let (panel_x, panel_y) = Self::params_panel_origin();
+        let row_height = 25.0;
+        let panel_height = row_height * (PARAM_ROWS.len() + 1) as f32 + 10.0;
+        draw_rectangle(panel_x, panel_y, 200.0, panel_height, self.theme.panel);
+
+        let button_size = 15.0;
+        let values = self.param_values();
+        for (row, (label, _)) in PARAM_ROWS.iter().enumerate() {
+            let row_y = panel_y + 20.0 + row as f32 * row_height;
+            draw_text(label, panel_x + 10.0, row_y, 15.0, self.theme.text);
+
+            // [-] value [+]
+            let minus_x = panel_x + 110.0;
+            let plus_x = panel_x + 170.0;
+            draw_rectangle(
+                minus_x,
+                row_y - button_size,
+                button_size,
+                button_size,
+                self.theme.control,
+            );
+            draw_text("-", minus_x + 5.0, row_y - 3.0, 15.0, self.theme.text);
+            draw_text(
+                &values[row].to_string(),
+                minus_x + button_size + 8.0,
+                row_y,
+                15.0,
+                self.theme.text,
+            );
+            draw_rectangle(
+                plus_x,
+                row_y - button_size,
+                button_size,
+                button_size,
+                self.theme.control,
+            );
+            draw_text("+", plus_x + 4.0, row_y - 3.0, 15.0, self.theme.text);
+        }
+
+        // Eval order, one letter per direction. Clicking a letter moves it one
+        // place later
+        let order_y = panel_y + 20.0 + PARAM_ROWS.len() as f32 * row_height;
+        draw_text("Order:", panel_x + 10.0, order_y, 15.0, self.theme.text);
+        for (i, direction) in self.params.shortcut_eval_order.iter().enumerate() {
+            let letter_x = panel_x + 110.0 + i as f32 * (button_size + 5.0);
+            draw_rectangle(
+                letter_x,
+                order_y - button_size,
+                button_size,
+                button_size,
+                self.theme.control,
+            );
+            draw_text(
+                &direction.letter().to_string(),
+                letter_x + 3.0,
+                order_y - 3.0,
+                15.0,
+                self.theme.text,
+            );
+        }
+    }
+
+    fn update_params_panel(&mut self) {
+        if !is_mouse_button_pressed(MouseButton::Left) {
+            return;
+        }
+        let (panel_x, panel_y) = Self::params_panel_origin();
+        let row_height = 25.0;
+        let button_size = 15.0;
+        let mouse_pos = mouse_position();
+        let over = |x: f32, y: f32| {
+            mouse_pos.0 >= x
+                && mouse_pos.0 <= x + button_size
+                && mouse_pos.1 >= y - button_size
+                && mouse_pos.1 <= y
+        };
+
+        let mut changed = false;
+        for (row, &(_, step)) in PARAM_ROWS.iter().enumerate() {
+            let row_y = panel_y + 20.0 + row as f32 * row_height;
+            let value = self.param_value_mut(row);
+            if over(panel_x + 110.0, row_y) {
+                *value = value.saturating_sub(step);
+                changed = true;
+            } else if over(panel_x + 170.0, row_y) {
+                *value += step;
+                changed = true;
+            }
+        }
+
+        let order_y = panel_y + 20.0 + PARAM_ROWS.len() as f32 * row_height;
+        let order = &mut self.params.shortcut_eval_order;
+        let order_len = order.len();
+        for i in 0..order_len {
+            if over(panel_x + 110.0 + i as f32 * (button_size + 5.0), order_y) {
+                order.swap(i, (i + 1) % order_len);
+                changed = true;
             }
         }
+
+        if changed {
+            self.strategy.set_params(&self.params);
+        }
+    }
+
+    // The values shown in the tuning panel, in `PARAM_ROWS` order
+    fn param_values(&self) -> [usize; 4] {
+        [
+            self.params.growth_buffer,
+            self.params.food_value,
+            self.params.future_food_penalty,
+            self.params.empty_square_factor,
+        ]
+    }
+
+    fn param_value_mut(&mut self, row: usize) -> &mut usize {
+        match row {
+            0 => &mut self.params.growth_buffer,
+            1 => &mut self.params.food_value,
+            2 => &mut self.params.future_food_penalty,
+            _ => &mut self.params.empty_square_factor,
+        }
     }
 
-    fn spawn_apple(&self, open_cells: &[(usize, usize)]) -> (usize, usize) {
-        let apple_index = rand::gen_range(0, open_cells.len());
-        open_cells[apple_index]
+    // Hand the snake between the bot and the player without restarting the game
+    fn toggle_mode(&mut self) {
+        if self.is_replay() {
+            return;
+        }
+        self.mode = match self.mode {
+            ControlMode::Bot => {
+                self.input.clear();
+                ControlMode::Human
+            }
+            ControlMode::Human => ControlMode::Bot,
+        };
     }
 
     fn draw_apple(&self) {
         // Calculate cell coordinates
-        let apple_x = self.apple.0 as f32 * CELL_SIZE;
-        let apple_y = self.apple.1 as f32 * CELL_SIZE;
+        let (apple_x, apple_y) = self.sim.apple();
+        let apple_x = apple_x as f32 * CELL_SIZE;
+        let apple_y = apple_y as f32 * CELL_SIZE;
 
         // Calculate center of the cell accounting for gap
         let center_x = apple_x + CELL_SIZE / 2.0;
@@ -269,76 +1209,371 @@ impl Game {
         let radius = (CELL_SIZE - CELL_GAP) / 2.0;
 
         // Draw the apple as a circle
-        draw_circle(center_x, center_y, radius, RED);
+        draw_circle(center_x, center_y, radius, self.theme.apple);
     }
 
-    fn draw_score(&self) {
-        let score_text = format!("Score: {}", self.score);
-        draw_text(&score_text, 10.0, 20.0, 20.0, WHITE);
+    fn draw_score(&self, overlay: Option<&CycleOverlay>) {
+        let score_text = format!("Score: {}", self.sim.score());
+        draw_text(&score_text, 10.0, 20.0, 20.0, self.theme.text);
+
+        let seed_text = format!("Seed: {}", self.sim.seed());
+        draw_text(&seed_text, 10.0, 38.0, 15.0, self.theme.text);
+
+        let controller_text = match (&self.state, self.mode) {
+            (GameState::Replay(playback), _) => format!(
+                "Replay of {}: move {}/{}{}",
+                if playback.replay().human {
+                    "a player"
+                } else {
+                    &playback.replay().strategy
+                },
+                playback.position(),
+                playback.len(),
+                if playback.paused { " (paused)" } else { "" }
+            ),
+            (_, ControlMode::Bot) => "Controller: Bot (Tab to take over)".to_owned(),
+            (_, ControlMode::Human) => "Controller: Human (Tab to hand back)".to_owned(),
+        };
+        draw_text(&controller_text, 10.0, 54.0, 15.0, self.theme.text);
+
+        if let Some(status) = &self.status {
+            draw_text(status, 10.0, 70.0, 15.0, self.theme.text);
+        }
+
+        // The distances the shortcut bot weighs up, counted along the cycle
+        if let Some(overlay) = overlay {
+            let (_, tail) = self.head_and_tail();
+            let describe = |distance: Option<usize>| match distance {
+                Some(distance) => distance.to_string(),
+                None => "-".to_owned(),
+            };
+            let distances_text = format!(
+                "Along the cycle: apple {}, tail {}",
+                describe(overlay.distance(self.sim.apple())),
+                describe(overlay.distance(tail))
+            );
+            draw_text(&distances_text, 10.0, 86.0, 15.0, self.theme.text);
+        }
+
+        if self.show_decision {
+            let decision_text = match &self.decision {
+                Some(decision) => decision_summary(decision),
+                None => "Bot: no decision to show".to_owned(),
+            };
+            draw_text(&decision_text, 10.0, 102.0, 15.0, self.theme.text);
+        }
     }
 
-    fn draw_game_over(&self) {
-        let game_over_text = "Game Over! Press Enter to restart or Escape to exit.";
+    // The end of game overlay: how it ended, then `prompt` for what to do next
+    fn draw_game_over(&self, outcome: Outcome, prompt: &str) {
+        let moves = self.sim.moves().len();
+        let (title, summary) = match outcome {
+            Outcome::Won => {
+                // A replay is watched at whatever speed, so its time means nothing
+                let time = if self.is_replay() {
+                    String::new()
+                } else {
+                    let seconds = self.play_time as u32;
+                    format!("   Time: {}:{:02}", seconds / 60, seconds % 60)
+                };
+                (
+                    "Board filled!",
+                    format!(
+                        "Moves: {}{}   Efficiency: {:.0}%",
+                        moves,
+                        time,
+                        self.sim.efficiency() * 100.0
+                    ),
+                )
+            }
+            Outcome::Died(cause) => (
+                "Game Over!",
+                format!("The snake {} after {} moves", cause.describe(), moves),
+            ),
+        };
+        let game_over_text = prompt;
         //center text based on screen size and place towards the bottom
         let screen_width = screen_width();
         let screen_height = screen_height();
         let text_width = measure_text(game_over_text, None, 20, 1.0).width;
         let text_x = (screen_width - text_width) / 2.0;
         let text_y = screen_height - 50.0;
-        draw_rectangle(
-            0.0,
-            0.0,
-            screen_width,
-            screen_height,
-            Color::new(0.0, 0.0, 0.0, 0.5),
+        draw_rectangle(0.0, 0.0, screen_width, screen_height, self.theme.panel);
+        draw_text(game_over_text, text_x, text_y, 20.0, self.theme.text);
+
+        let title_width = measure_text(title, None, 40, 1.0).width;
+        let title_y = screen_height / 2.0 - 20.0;
+        draw_text(
+            title,
+            (screen_width - title_width) / 2.0,
+            title_y,
+            40.0,
+            self.theme.text,
+        );
+        let summary_width = measure_text(&summary, None, 20, 1.0).width;
+        draw_text(
+            &summary,
+            (screen_width - summary_width) / 2.0,
+            title_y + 30.0,
+            20.0,
+            self.theme.text,
         );
-        draw_text(&game_over_text, text_x, text_y, 20.0, WHITE);
     }
 
     pub fn handle_input(&mut self) {
-        if self.is_over {
-            if is_key_pressed(KeyCode::Enter) {
-                self.reset();
-            } else if is_key_pressed(KeyCode::Escape) {
-                std::process::exit(0);
+        match self.state {
+            GameState::MainMenu => self.handle_menu_input(),
+            GameState::Playing | GameState::Paused => self.handle_play_input(),
+            GameState::Won | GameState::Died(_) => {
+                if is_key_pressed(KeyCode::Comma) {
+                    self.step_back();
+                } else if is_key_pressed(KeyCode::Enter) {
+                    self.start_game(self.mode);
+                } else if is_key_pressed(KeyCode::R) {
+                    self.save_replay();
+                } else if is_key_pressed(KeyCode::Escape) {
+                    self.state = GameState::MainMenu;
+                }
             }
-            return;
+            GameState::Replay(_) => self.handle_replay_input(),
         }
     }
 
-    fn check_for_death(&mut self) {
-        if self.snake.is_dead() {
-            self.is_over = true;
+    fn handle_menu_input(&mut self) {
+        if is_key_pressed(KeyCode::Down) {
+            self.menu_selection = (self.menu_selection + 1) % MENU_ITEMS.len();
+        } else if is_key_pressed(KeyCode::Up) {
+            self.menu_selection = (self.menu_selection + MENU_ITEMS.len() - 1) % MENU_ITEMS.len();
+        } else if is_key_pressed(KeyCode::Escape) {
+            std::process::exit(0);
+        }
+
+        let item = MENU_ITEMS[self.menu_selection];
+        if item == MenuItem::ChooseBot
+            && (is_key_pressed(KeyCode::Left) || is_key_pressed(KeyCode::Right))
+        {
+            self.set_strategy(self.strategy_kind.next());
+        }
+        if is_key_pressed(KeyCode::Enter) {
+            match item {
+                MenuItem::WatchBot => self.start_game(ControlMode::Bot),
+                MenuItem::PlayYourself => self.start_game(ControlMode::Human),
+                MenuItem::ChooseBot => self.set_strategy(self.strategy_kind.next()),
+                MenuItem::Quit => std::process::exit(0),
+            }
         }
     }
 
-    fn reset(&mut self) {
-        let grid_width = screen_width() / CELL_SIZE;
-        let grid_height = screen_height() / CELL_SIZE;
-
-        let snake = Snake::spawn_on_map(5, 5, 4);
-        // open_cells should be a vector of tuples (x, y) representing the available cells for the apple i.e entire map - snake cells
-        let mut open_cells = Vec::new();
-        for x in 0..(grid_width as usize) {
-            for y in 0..(grid_height as usize) {
-                if !snake.segments.iter().any(|s| s.cur == (x, y)) {
-                    open_cells.push((x, y));
+    fn handle_play_input(&mut self) {
+        if is_key_pressed(KeyCode::Space) {
+            self.state = match self.state {
+                GameState::Paused => GameState::Playing,
+                _ => GameState::Paused,
+            };
+        }
+
+        if is_key_pressed(KeyCode::Escape) {
+            self.state = match self.state {
+                GameState::Paused => GameState::MainMenu,
+                _ => GameState::Paused,
+            };
+            return;
+        }
+
+        if is_key_pressed(KeyCode::R) {
+            self.save_replay();
+        }
+
+        if is_key_pressed(KeyCode::F5) {
+            self.save_game();
+        }
+
+        if is_key_pressed(KeyCode::Tab) {
+            self.toggle_mode();
+        }
+
+        if is_key_pressed(KeyCode::P) {
+            self.show_params = !self.show_params;
+        }
+
+        if is_key_pressed(KeyCode::O) {
+            self.cycle_overlay_mode();
+        }
+
+        if is_key_pressed(KeyCode::T) {
+            self.next_theme();
+        }
+
+        if is_key_pressed(KeyCode::I) {
+            self.show_decision = !self.show_decision;
+            let show_decision = self.show_decision;
+            self.change_settings(|settings| settings.show_decision = show_decision);
+        }
+
+        // Single-stepping pauses first, so the step is all that happens
+        if is_key_pressed(KeyCode::Period) {
+            self.state = GameState::Paused;
+            self.step_once();
+        } else if is_key_pressed(KeyCode::Comma) {
+            self.step_back();
+        }
+
+        // While paused, turns queue up for the next single step
+        if self.mode == ControlMode::Human {
+            let current_direction = self.sim.snake().direction();
+            for (key, direction) in ARROW_KEYS {
+                if is_key_pressed(key) {
+                    self.input.push(direction.delta(), current_direction);
                 }
             }
         }
+    }
+
+    // Save the whole game so it can be picked up again with --resume
+    fn save_game(&mut self) {
+        let saved = SavedGame {
+            sim: self.sim.clone(),
+            fixed_seed: self.fixed_seed,
+            human: self.mode == ControlMode::Human,
+            strategy: self.strategy_kind.name().to_owned(),
+            strategy_state: self.strategy.save_state(),
+            params: self.params.clone(),
+            speed_multiplier: self.speed_multiplier,
+            step_timer: self.step_timer,
+            play_time: self.play_time,
+            draw_cycle: self.draw_cycle,
+        };
+        self.status = Some(match saved.save() {
+            Ok(path) => format!("Saved game to {}", path.display()),
+            Err(err) => format!("Could not save the game: {}", err),
+        });
+    }
+
+    // Space pauses, Left and Right step one move, Up and Down jump further
+    fn handle_replay_input(&mut self) {
+        let GameState::Replay(playback) = &mut self.state else {
+            return;
+        };
+        let position = playback.position();
+
+        if self.sim.is_over() && is_key_pressed(KeyCode::Enter) {
+            playback.paused = false;
+            self.seek_replay(0);
+        } else if is_key_pressed(KeyCode::Escape) {
+            // Leave the replay behind for a fresh game of our own
+            self.state = GameState::MainMenu;
+            self.reset();
+        } else if is_key_pressed(KeyCode::Space) {
+            playback.paused = !playback.paused;
+        } else if is_key_pressed(KeyCode::Right) {
+            playback.paused = true;
+            self.seek_replay(position + 1);
+        } else if is_key_pressed(KeyCode::Left) {
+            playback.paused = true;
+            self.seek_replay(position.saturating_sub(1));
+        } else if is_key_pressed(KeyCode::Up) {
+            self.seek_replay(position + REPLAY_SEEK_STEP);
+        } else if is_key_pressed(KeyCode::Down) {
+            self.seek_replay(position.saturating_sub(REPLAY_SEEK_STEP));
+        }
+    }
+
+    fn is_replay(&self) -> bool {
+        matches!(self.state, GameState::Replay(_))
+    }
+
+    // Start a fresh game, steered by the bot or the player
+    fn start_game(&mut self, mode: ControlMode) {
+        self.reset();
+        self.mode = ControlMode::Bot;
+        if mode == ControlMode::Human {
+            self.toggle_mode();
+        }
+        self.state = GameState::Playing;
+    }
 
-        let apple_index = rand::gen_range(0, open_cells.len());
-        let apple = open_cells[apple_index];
-        let (cycle, tour_numbers) =
-            generate_hamiltonian_cycle(grid_width as i32, grid_height as i32);
+    fn reset(&mut self) {
+        match Self::new_simulation(self.board, self.spawn, self.fixed_seed) {
+            Ok(sim) => self.reset_to(sim),
+            Err(err) => self.status = Some(format!("Could not restart: {}", err)),
+        }
+    }
 
-        self.score = 0;
-        self.is_over = false;
+    // Start over on `sim` with a fresh bot
+    fn reset_to(&mut self, sim: Simulation) {
+        self.sim = sim;
+        self.strategy = self.strategy_kind.build(&self.params);
+        self.input.clear();
         self.step_timer = 0.0;
-        self.open_cells = open_cells;
-        self.apple = apple;
-        self.snake = snake;
-        self.cycle = cycle;
-        self.tour_numbers = tour_numbers;
+        self.play_time = 0.0;
+        self.status = None;
+        self.history.clear();
+        self.decision = None;
+    }
+}
+
+// Maps board pixels, CELL_SIZE to a cell, onto a window of `screen` pixels: as
+// large as fits without stretching, centred along the other side, with row 0
+// at the top. Drawing to the screen, `from_display_rect` puts y = 0 at the
+// bottom, so the rect is given upside down
+fn board_camera((width, height): (usize, usize), screen: (f32, f32)) -> Camera2D {
+    let board_width = width as f32 * CELL_SIZE;
+    let board_height = height as f32 * CELL_SIZE;
+    let scale = (screen.0 / board_width).min(screen.1 / board_height);
+    let view_width = screen.0 / scale;
+    let view_height = screen.1 / scale;
+    let top = (board_height - view_height) / 2.0;
+    Camera2D::from_display_rect(Rect::new(
+        (board_width - view_width) / 2.0,
+        top + view_height,
+        view_width,
+        -view_height,
+    ))
+}
+
+fn draw_check_mark(checkbox_x: f32, checkbox_y: f32, checkbox_size: f32, color: Color) {
+    draw_line(
+        checkbox_x + 2.0,
+        checkbox_y - checkbox_size / 2.0,
+        checkbox_x + checkbox_size / 2.0,
+        checkbox_y - 2.0,
+        2.0,
+        color,
+    );
+    draw_line(
+        checkbox_x + checkbox_size / 2.0,
+        checkbox_y - 2.0,
+        checkbox_x + checkbox_size - 2.0,
+        checkbox_y - checkbox_size + 2.0,
+        2.0,
+        color,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Where a board pixel lands on the window, from (-1, -1) at the bottom
+    // left to (1, 1) at the top right
+    fn on_screen(camera: &Camera2D, (x, y): (f32, f32)) -> (f32, f32) {
+        let point = camera.matrix().transform_point3(vec3(x, y, 0.0));
+        (
+            (point.x * 100.0).round() / 100.0,
+            (point.y * 100.0).round() / 100.0,
+        )
+    }
+
+    #[test]
+    fn board_camera_puts_the_first_cell_top_left() {
+        let camera = board_camera((10, 10), (500.0, 500.0));
+        assert_eq!(on_screen(&camera, (0.0, 0.0)), (-1.0, 1.0));
+        let far_corner = 10.0 * CELL_SIZE;
+        assert_eq!(on_screen(&camera, (far_corner, far_corner)), (1.0, -1.0));
+
+        // A wide window centres the board, still with row 0 along the top
+        let camera = board_camera((10, 10), (1000.0, 500.0));
+        assert_eq!(on_screen(&camera, (0.0, 0.0)), (-0.5, 1.0));
+        assert_eq!(on_screen(&camera, (far_corner, far_corner)), (0.5, -1.0));
     }
 }
@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+
+// Enough to catch a quick double turn without letting the snake run on autopilot
+const MAX_QUEUED_TURNS: usize = 3;
+
+/// Turns requested by the player, applied one per move so that two quick key
+/// presses between moves both take effect.
+pub struct DirectionQueue {
+    turns: VecDeque<(i32, i32)>,
+}
+
+impl DirectionQueue {
+    pub fn new() -> Self {
+        Self {
+            turns: VecDeque::new(),
+        }
+    }
+
+    // Queue a turn relative to where the snake will be heading once the turns
+    // already queued have been taken. Repeats and reversals are ignored.
+    pub fn push(&mut self, direction: (i32, i32), current_direction: (i32, i32)) {
+        let heading = self.turns.back().copied().unwrap_or(current_direction);
+        if direction == heading || direction == (-heading.0, -heading.1) {
+            return;
+        }
+        if self.turns.len() < MAX_QUEUED_TURNS {
+            self.turns.push_back(direction);
+        }
+    }
+
+    // The direction for the next move: the oldest queued turn, or straight on
+    pub fn next(&mut self, current_direction: (i32, i32)) -> (i32, i32) {
+        self.turns.pop_front().unwrap_or(current_direction)
+    }
+
+    pub fn clear(&mut self) {
+        self.turns.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RIGHT: (i32, i32) = (1, 0);
+    const LEFT: (i32, i32) = (-1, 0);
+    const DOWN: (i32, i32) = (0, 1);
+    const UP: (i32, i32) = (0, -1);
+
+    #[test]
+    fn two_quick_turns_both_apply() {
+        let mut queue = DirectionQueue::new();
+        // A U-turn pressed between two moves
+        queue.push(DOWN, RIGHT);
+        queue.push(LEFT, RIGHT);
+        assert_eq!(queue.next(RIGHT), DOWN);
+        assert_eq!(queue.next(DOWN), LEFT);
+        assert_eq!(queue.next(LEFT), LEFT);
+    }
+
+    #[test]
+    fn reversing_the_last_queued_turn_is_ignored() {
+        let mut queue = DirectionQueue::new();
+        queue.push(DOWN, RIGHT);
+        // Back on itself once the queued turn is taken, though not relative
+        // to where the snake is heading now
+        queue.push(UP, RIGHT);
+        // A repeat of the queued turn adds nothing either
+        queue.push(DOWN, RIGHT);
+        assert_eq!(queue.next(RIGHT), DOWN);
+        assert_eq!(queue.next(DOWN), DOWN);
+    }
+
+    #[test]
+    fn only_so_many_turns_queue_up() {
+        let mut queue = DirectionQueue::new();
+        let turns = [DOWN, LEFT, UP, RIGHT, DOWN];
+        for turn in turns {
+            queue.push(turn, RIGHT);
+        }
+        for &turn in &turns[..MAX_QUEUED_TURNS] {
+            assert_eq!(queue.next(RIGHT), turn);
+        }
+        assert_eq!(queue.next(RIGHT), RIGHT);
+    }
+}
@@ -1,27 +1,105 @@
 use macroquad::prelude::*;
 
+mod bench;
 mod bot;
+mod direction;
+mod error;
+mod files;
 mod game;
+mod input;
+mod options;
+mod overlay;
+mod params;
+mod render;
+mod replay;
+mod rng;
+mod save;
+mod settings;
+mod sim;
 mod snake;
+mod sprites;
+mod strategy;
+mod theme;
 
+use crate::bench::BenchOptions;
 use crate::game::Game;
+use crate::options::{GameOptions, Start, MAX_WINDOW_SIZE};
+use crate::settings::Settings;
 
-fn window_conf() -> Conf {
+// The window fits the board at the configured cell size, or at smaller cells
+// if it would not fit on a screen otherwise: the replay's or the saved game's
+// board when there is one
+fn window_conf(options: &GameOptions) -> Conf {
+    let config = &options.config;
+    let (width, height) = match &options.start {
+        Start::New => (config.width, config.height),
+        Start::Replay(replay) => (replay.width, replay.height),
+        Start::Resume(saved) => (saved.sim.width(), saved.sim.height()),
+    };
+    let cell_size = config
+        .cell_size
+        .min(MAX_WINDOW_SIZE / width.max(height).max(1) as f32);
     Conf {
         window_title: "Snake".to_owned(),
-        window_width: 500,
-        window_height: 500,
-        window_resizable: false,
+        window_width: (width as f32 * cell_size) as i32,
+        window_height: (height as f32 * cell_size) as i32,
+        window_resizable: true,
         ..Default::default()
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
-    let mut game = Game::new();
+fn main() {
+    // `snake bench ...` plays games headlessly instead of opening the window
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("bench") {
+        let options = match BenchOptions::parse(&args[2..]) {
+            Ok(options) => options,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(2);
+            }
+        };
+        if let Err(err) = bench::run(&options) {
+            eprintln!("Benchmark failed: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Read everything before opening the window, so bad arguments fail fast
+    let settings = match Settings::load() {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("Could not load settings: {}", err);
+            std::process::exit(2);
+        }
+    };
+    let options = match GameOptions::parse(&args[1..], settings) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    macroquad::Window::from_config(window_conf(&options), run_game(options));
+}
+
+async fn run_game(options: GameOptions) {
+    let GameOptions { config, start } = options;
+    let game = match start {
+        Start::New => Game::new(config),
+        Start::Replay(replay) => Game::from_replay(replay, config),
+        Start::Resume(saved) => Ok(Game::from_saved(*saved, config)),
+    };
+    let mut game = match game {
+        Ok(game) => game,
+        Err(err) => {
+            eprintln!("Could not start the game: {}", err);
+            std::process::exit(1);
+        }
+    };
 
     loop {
-        clear_background(BLACK);
         game.handle_input();
         game.update(get_frame_time());
         game.render();
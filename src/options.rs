@@ -0,0 +1,315 @@
+use std::path::{Path, PathBuf};
+
+use crate::params::BotParams;
+use crate::replay::Replay;
+use crate::save::SavedGame;
+use crate::settings::Settings;
+use crate::sim::Simulation;
+use crate::snake::Spawn;
+use crate::strategy::StrategyKind;
+
+// The window the game opens in when no board size is given
+const DEFAULT_WINDOW_SIZE: f32 = 500.0;
+
+// The longest side of a window the game opens. Boards that would not fit at
+// their cell size get smaller cells instead
+pub const MAX_WINDOW_SIZE: f32 = 2000.0;
+
+// The most cells a board can have along either side. Anything much larger
+// takes too long to set up and leaves cells too small to see
+pub const MAX_BOARD_SIDE: usize = 200;
+
+// The speed slider's range
+pub const MIN_SPEED: f32 = 0.5;
+pub const MAX_SPEED: f32 = 100.0;
+
+// Cells smaller than this are too small to see the snake in, and larger ones
+// leave no room for a board on most screens
+pub const MIN_CELL_SIZE: f32 = 5.0;
+pub const MAX_CELL_SIZE: f32 = 100.0;
+
+const USAGE: &str = "usage: snake [--size <width>x<height>] [--cell-size <pixels>] \
+                     [--speed <multiplier>] [--seed <n>] [--length <n>] [--start <x>,<y>] \
+                     [--strategy <name>] [--params <file>] [--assets <dir>] \
+                     [--replay <file> | --resume <file>]";
+
+/// How new games are set up: the board, the snake and the bot.
+pub struct GameConfig {
+    pub width: usize,
+    pub height: usize,
+    /// Pixels per cell when the window opens.
+    pub cell_size: f32,
+    /// The speed multiplier the game starts at.
+    pub speed: f32,
+    /// Every game is played from this seed, or from the clock when `None`.
+    pub seed: Option<u64>,
+    pub spawn: Spawn,
+    pub strategy: StrategyKind,
+    pub params: BotParams,
+    /// Where the sprite atlas is loaded from.
+    pub assets: PathBuf,
+    /// The settings file as it was read, for the game to update and write back.
+    pub settings: Settings,
+}
+
+/// What the window opens on.
+pub enum Start {
+    New,
+    Replay(Replay),
+    Resume(Box<SavedGame>),
+}
+
+/// Everything read from the command line when starting the game.
+pub struct GameOptions {
+    pub config: GameConfig,
+    pub start: Start,
+}
+
+impl GameOptions {
+    /// Parses the arguments after the program name, loading any files they
+    /// name and checking a board can be set up the way they describe. Anything
+    /// not given falls back to `settings`.
+    pub fn parse(args: &[String], settings: Settings) -> Result<Self, String> {
+        let mut config = GameConfig {
+            width: 0,
+            height: 0,
+            cell_size: settings.cell_size,
+            speed: settings.speed,
+            seed: None,
+            spawn: Spawn::default(),
+            strategy: settings.strategy_kind(),
+            params: BotParams::default(),
+            assets: settings.assets.clone(),
+            settings,
+        };
+        let mut size = config.settings.width.zip(config.settings.height);
+        let mut start = Start::New;
+        // Flags that describe a new board, which a replay or save brings its own of
+        let mut board_flags = Vec::new();
+
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} expects a value\n{}", flag, USAGE))?;
+            match flag.as_str() {
+                "--size" => {
+                    size = Some(parse_size(value)?);
+                    board_flags.push(flag);
+                }
+                "--cell-size" => {
+                    config.cell_size = value
+                        .parse()
+                        .ok()
+                        .filter(|size| (MIN_CELL_SIZE..=MAX_CELL_SIZE).contains(size))
+                        .ok_or_else(|| {
+                            format!(
+                                "--cell-size expects {} to {} pixels, got {:?}",
+                                MIN_CELL_SIZE, MAX_CELL_SIZE, value
+                            )
+                        })?;
+                }
+                "--speed" => {
+                    config.speed = value
+                        .parse()
+                        .ok()
+                        .filter(|speed| (MIN_SPEED..=MAX_SPEED).contains(speed))
+                        .ok_or_else(|| {
+                            format!(
+                                "--speed expects a multiplier from {} to {}, got {:?}",
+                                MIN_SPEED, MAX_SPEED, value
+                            )
+                        })?;
+                }
+                "--seed" => {
+                    config.seed = Some(value.parse().map_err(|_| {
+                        format!("--seed expects a non-negative integer, got {:?}", value)
+                    })?);
+                    board_flags.push(flag);
+                }
+                "--length" => {
+                    config.spawn.length = parse_length(value)?;
+                    board_flags.push(flag);
+                }
+                "--start" => {
+                    (config.spawn.x, config.spawn.y) = parse_start(value)?;
+                    board_flags.push(flag);
+                }
+                "--strategy" => {
+                    config.strategy = StrategyKind::parse(value)?;
+                    board_flags.push(flag);
+                }
+                "--params" => {
+                    config.params = BotParams::load(Path::new(value))
+                        .map_err(|err| format!("Could not load bot params: {}", err))?;
+                }
+                "--assets" => config.assets = PathBuf::from(value),
+                "--replay" | "--resume" if !matches!(start, Start::New) => {
+                    return Err("--replay and --resume cannot be used together".to_owned());
+                }
+                "--replay" => {
+                    start = Start::Replay(
+                        Replay::load(Path::new(value))
+                            .map_err(|err| format!("Could not load the replay: {}", err))?,
+                    );
+                }
+                "--resume" => {
+                    start = Start::Resume(Box::new(
+                        SavedGame::load(Path::new(value))
+                            .map_err(|err| format!("Could not resume the game: {}", err))?,
+                    ));
+                }
+                _ => return Err(format!("unknown option {:?}\n{}", flag, USAGE)),
+            }
+        }
+
+        if let (false, Some(flag)) = (matches!(start, Start::New), board_flags.first()) {
+            return Err(format!(
+                "{} cannot be used with --replay or --resume, which bring their own board",
+                flag
+            ));
+        }
+
+        // Without a size the board fills the default window
+        let cells = (DEFAULT_WINDOW_SIZE / config.cell_size) as usize;
+        (config.width, config.height) = size.unwrap_or((cells, cells));
+
+        check_board_size(config.width, config.height)
+            .map_err(|err| format!("Cannot set up the board: {}", err))?;
+        // Setting up a board runs every check the cycle generator and the snake
+        // make, and the seed does not change whether they pass
+        Simulation::with_spawn(config.width, config.height, 0, config.spawn)
+            .map_err(|err| format!("Cannot set up the board: {}", err))?;
+
+        Ok(GameOptions { config, start })
+    }
+}
+
+/// Checks a board is small enough to set up, before anything is allocated
+/// for it.
+pub fn check_board_size(width: usize, height: usize) -> Result<(), String> {
+    if width > MAX_BOARD_SIDE || height > MAX_BOARD_SIDE {
+        return Err(format!(
+            "a {}x{} board is too large, boards can have at most {} cells a side",
+            width, height, MAX_BOARD_SIDE
+        ));
+    }
+    Ok(())
+}
+
+/// Reads the value of `--size`, for the game and `snake bench` alike.
+pub fn parse_size(value: &str) -> Result<(usize, usize), String> {
+    value
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .ok_or_else(|| format!("--size expects <width>x<height>, got {:?}", value))
+}
+
+/// Reads the value of `--length`, for the game and `snake bench` alike.
+pub fn parse_length(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("--length expects a number, got {:?}", value))
+}
+
+/// Reads the value of `--start`, for the game and `snake bench` alike.
+pub fn parse_start(value: &str) -> Result<(usize, usize), String> {
+    value
+        .split_once(',')
+        .and_then(|(x, y)| Some((x.parse().ok()?, y.parse().ok()?)))
+        .ok_or_else(|| format!("--start expects <x>,<y>, got {:?}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<GameOptions, String> {
+        let args: Vec<String> = args.iter().map(|&arg| arg.to_owned()).collect();
+        GameOptions::parse(&args, Settings::default())
+    }
+
+    fn error(args: &[&str]) -> String {
+        match parse(args) {
+            Ok(_) => panic!("{:?} should have been rejected", args),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn board_flags_apply_to_new_games() {
+        let options = parse(&["--size", "12x8", "--start", "2,3", "--length", "3"]).unwrap();
+        assert!(matches!(options.start, Start::New));
+        assert_eq!((options.config.width, options.config.height), (12, 8));
+        assert_eq!((options.config.spawn.x, options.config.spawn.y), (2, 3));
+        assert_eq!(options.config.spawn.length, 3);
+    }
+
+    #[test]
+    fn a_replay_brings_its_own_board() {
+        let sim = Simulation::with_spawn(10, 10, 0, Spawn::default()).unwrap();
+        let replay = Replay::record(&sim, StrategyKind::HamiltonianShortcut, false);
+        let path = std::env::temp_dir().join(format!("snake-options-{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_string(&replay).unwrap()).unwrap();
+        let path = path.to_str().unwrap();
+
+        assert!(matches!(
+            parse(&["--replay", path]).unwrap().start,
+            Start::Replay(_)
+        ));
+        let err = error(&["--replay", path, "--size", "12x12"]);
+        assert!(
+            err.starts_with("--size cannot be used with --replay"),
+            "{}",
+            err
+        );
+        let err = error(&["--seed", "4", "--replay", path]);
+        assert!(
+            err.starts_with("--seed cannot be used with --replay"),
+            "{}",
+            err
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        for cell_size in ["4.9", "100.5", "big"] {
+            let err = error(&["--cell-size", cell_size]);
+            assert!(
+                err.starts_with("--cell-size expects 5 to 100 pixels"),
+                "{}",
+                err
+            );
+        }
+        for speed in ["0.4", "101", "-1"] {
+            let err = error(&["--speed", speed]);
+            assert!(err.starts_with("--speed expects a multiplier"), "{}", err);
+        }
+        assert!(parse(&["--cell-size", "5", "--speed", "100"]).is_ok());
+
+        let err = error(&["--size", "201x10"]);
+        assert!(err.contains("at most 200 cells a side"), "{}", err);
+    }
+
+    #[test]
+    fn malformed_values_are_rejected() {
+        for size in ["20", "20x", "x20", "20x-4", "20*20", "20x20x20"] {
+            assert!(parse_size(size).is_err(), "{:?}", size);
+        }
+        assert_eq!(parse_size("30x12"), Ok((30, 12)));
+
+        for start in ["5", "5,", "5;5", "-1,5", "5,5,5"] {
+            assert!(parse_start(start).is_err(), "{:?}", start);
+        }
+        assert_eq!(parse_start("0,7"), Ok((0, 7)));
+
+        let err = error(&["--size", "twentyxtwenty"]);
+        assert!(
+            err.starts_with("--size expects <width>x<height>"),
+            "{}",
+            err
+        );
+    }
+}
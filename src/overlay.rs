@@ -0,0 +1,223 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::bot::NOT_ON_CYCLE;
+use crate::render::{CELL_GAP, CELL_SIZE};
+use crate::strategy::{Branch, Decision};
+use crate::theme::Theme;
+
+/// Extra information drawn over the board, for seeing what the bot sees.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Overlay {
+    Off,
+    /// Each cell's position along the cycle.
+    TourNumbers,
+    /// Cells shaded by how far along the cycle they are from the head.
+    Heatmap,
+}
+
+impl Overlay {
+    pub fn name(self) -> &'static str {
+        match self {
+            Overlay::Off => "off",
+            Overlay::TourNumbers => "tour numbers",
+            Overlay::Heatmap => "heatmap",
+        }
+    }
+
+    pub fn next(self) -> Overlay {
+        match self {
+            Overlay::Off => Overlay::TourNumbers,
+            Overlay::TourNumbers => Overlay::Heatmap,
+            Overlay::Heatmap => Overlay::Off,
+        }
+    }
+}
+
+/// The cycle the bot is following, numbered and measured from the head the
+/// same way the bot measures `distance_to_food` and `distance_to_tail`.
+pub struct CycleOverlay {
+    width: usize,
+    height: usize,
+    tour_numbers: Vec<usize>,
+    cycle_len: usize,
+    // The head's tour number, `None` once it has left the board
+    head_tour: Option<usize>,
+}
+
+impl CycleOverlay {
+    pub fn new(
+        cycle: &[(usize, usize)],
+        width: usize,
+        height: usize,
+        head: (usize, usize),
+    ) -> Self {
+        // Number the cells here rather than borrowing the game's numbers, so a
+        // cycle a bot has reshaped is numbered the way it is drawn
+        let mut tour_numbers = vec![NOT_ON_CYCLE; width * height];
+        for (i, &(x, y)) in cycle.iter().enumerate() {
+            tour_numbers[x + y * width] = i;
+        }
+        let mut overlay = CycleOverlay {
+            width,
+            height,
+            tour_numbers,
+            cycle_len: cycle.len(),
+            head_tour: None,
+        };
+        overlay.head_tour = overlay.tour_number(head);
+        overlay
+    }
+
+    fn tour_number(&self, (x, y): (usize, usize)) -> Option<usize> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.tour_numbers[x + y * self.width]).filter(|&number| number != NOT_ON_CYCLE)
+    }
+
+    /// Moves along the cycle from the head to `cell`.
+    pub fn distance(&self, cell: (usize, usize)) -> Option<usize> {
+        let head = self.head_tour?;
+        let target = self.tour_number(cell)?;
+        Some((target + self.cycle_len - head) % self.cycle_len)
+    }
+
+    /// Shades every cell on the cycle from hot just ahead of the head to cold
+    /// just behind it. Drawn under the snake.
+    pub fn draw_heatmap(&self, theme: &Theme) {
+        let (near, far) = (theme.heat_near.to_vec(), theme.heat_far.to_vec());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let Some(distance) = self.distance((x, y)) else {
+                    continue;
+                };
+                let t = distance as f32 / self.cycle_len.max(1) as f32;
+                draw_rectangle(
+                    x as f32 * CELL_SIZE + CELL_GAP / 2.0,
+                    y as f32 * CELL_SIZE + CELL_GAP / 2.0,
+                    CELL_SIZE - CELL_GAP,
+                    CELL_SIZE - CELL_GAP,
+                    Color::from_vec(near.lerp(far, t)),
+                );
+            }
+        }
+    }
+
+    /// Prints each cell's tour number in its corner. Drawn over the snake, so
+    /// the body's numbers stay readable.
+    pub fn draw_tour_numbers(&self, theme: &Theme) {
+        let font_size = CELL_SIZE * 0.4;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if let Some(number) = self.tour_number((x, y)) {
+                    draw_text(
+                        &number.to_string(),
+                        x as f32 * CELL_SIZE + CELL_GAP,
+                        y as f32 * CELL_SIZE + font_size,
+                        font_size,
+                        theme.tour_numbers,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Outlines the head, tail and apple cells so they stand out on the cycle.
+pub fn draw_markers(
+    head: (usize, usize),
+    tail: (usize, usize),
+    apple: (usize, usize),
+    theme: &Theme,
+) {
+    for (cell, color) in [
+        (apple, theme.apple_marker),
+        (tail, theme.tail_marker),
+        (head, theme.head_marker),
+    ] {
+        draw_rectangle_lines(
+            cell.0 as f32 * CELL_SIZE,
+            cell.1 as f32 * CELL_SIZE,
+            CELL_SIZE,
+            CELL_SIZE,
+            2.0,
+            color,
+        );
+    }
+}
+
+/// Shades the cells the bot weighed up for its last move by whether they were
+/// within its budget, would have cut too far, were blocked or were off the
+/// cycle, each with its distance along the cycle. The move it made is outlined.
+pub fn draw_decision(decision: &Decision, theme: &Theme) {
+    let budget = decision
+        .budget
+        .as_ref()
+        .map(|budget| budget.cutting_amount_available);
+    for candidate in &decision.candidates {
+        let (x, y) = (
+            candidate.cell.0 as f32 * CELL_SIZE,
+            candidate.cell.1 as f32 * CELL_SIZE,
+        );
+        let color = match (candidate.blocked, candidate.dist_on_cycle, budget) {
+            (true, _, _) => theme.blocked,
+            (false, Some(distance), Some(budget)) if distance <= budget => theme.within_budget,
+            (false, Some(_), _) => theme.over_budget,
+            (false, None, _) => theme.off_cycle,
+        };
+        draw_rectangle(
+            x + CELL_GAP / 2.0,
+            y + CELL_GAP / 2.0,
+            CELL_SIZE - CELL_GAP,
+            CELL_SIZE - CELL_GAP,
+            Color::new(color.r, color.g, color.b, 0.4),
+        );
+        if let Some(distance) = candidate.dist_on_cycle {
+            draw_text(
+                &format!("+{}", distance),
+                x + CELL_GAP,
+                y + CELL_SIZE - CELL_GAP * 2.0,
+                CELL_SIZE * 0.5,
+                theme.text,
+            );
+        }
+    }
+
+    let chosen = (
+        decision.head.0 as i32 + decision.direction.0,
+        decision.head.1 as i32 + decision.direction.1,
+    );
+    draw_rectangle_lines(
+        chosen.0 as f32 * CELL_SIZE,
+        chosen.1 as f32 * CELL_SIZE,
+        CELL_SIZE,
+        CELL_SIZE,
+        3.0,
+        theme.chosen,
+    );
+}
+
+/// One line on what the bot did and the numbers behind it.
+pub fn decision_summary(decision: &Decision) -> String {
+    let chosen = decision
+        .candidates
+        .iter()
+        .find(|candidate| candidate.direction == decision.direction)
+        .and_then(|candidate| candidate.dist_on_cycle);
+    let branch = match (decision.branch, chosen) {
+        (Branch::Shortcut, Some(distance)) => format!("shortcut +{}", distance),
+        (branch, _) => branch.describe().to_owned(),
+    };
+    match &decision.budget {
+        Some(budget) => format!(
+            "Bot: {} (apple {}, tail {}, budget {})",
+            branch,
+            budget.distance_to_food,
+            budget.distance_to_tail,
+            budget.cutting_amount_available
+        ),
+        None => format!("Bot: {}", branch),
+    }
+}
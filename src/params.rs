@@ -0,0 +1,70 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::direction::Direction;
+use crate::error::FileError;
+use crate::files::read_toml;
+
+/// Knobs for the shortcut bot, trading risk against speed. Every field is
+/// optional in a params file; missing ones keep their default.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotParams {
+    /// Cells of slack kept between the head and the tail when cutting across
+    /// the cycle.
+    pub growth_buffer: usize,
+    /// How much the snake grows from one apple.
+    pub food_value: usize,
+    /// Extra slack kept back when the apple lies between the head and the tail
+    /// and the free space is getting tight.
+    pub future_food_penalty: usize,
+    /// The penalty applies once the head-to-tail gap past the apple, times this
+    /// factor, is more than the number of empty cells.
+    pub empty_square_factor: usize,
+    /// The order shortcut directions are tried in. Ties go to the earliest.
+    pub shortcut_eval_order: [Direction; 4],
+}
+
+impl Default for BotParams {
+    fn default() -> Self {
+        Self {
+            growth_buffer: 25,
+            food_value: 1,
+            future_food_penalty: 10,
+            empty_square_factor: 4,
+            shortcut_eval_order: Direction::ALL,
+        }
+    }
+}
+
+impl BotParams {
+    /// Reads params from a TOML file, e.g.
+    ///
+    /// ```toml
+    /// growth_buffer = 10
+    /// shortcut_eval_order = ["up", "left", "down", "right"]
+    /// ```
+    pub fn load(path: &Path) -> Result<Self, FileError> {
+        let params: BotParams = read_toml(path)?;
+
+        // Every direction has to be tried, or the bot can miss the way along the cycle
+        if Direction::ALL
+            .iter()
+            .any(|direction| !params.shortcut_eval_order.contains(direction))
+        {
+            return Err(FileError::Invalid {
+                path: path.to_path_buf(),
+                message: "shortcut_eval_order must list each of right, left, down and up once"
+                    .to_owned(),
+            });
+        }
+
+        Ok(params)
+    }
+
+    // The shortcut evaluation order as (dx, dy) steps
+    pub fn eval_order(&self) -> [(i32, i32); 4] {
+        self.shortcut_eval_order.map(Direction::delta)
+    }
+}
@@ -0,0 +1,143 @@
+use macroquad::prelude::*;
+
+use crate::direction::Direction;
+use crate::snake::{Snake, SnakeSegment};
+use crate::theme::Theme;
+
+pub const CELL_SIZE: f32 = 25.;
+pub const CELL_GAP: f32 = 2.5;
+
+pub fn draw_snake(snake: &Snake, ratio: f32, theme: &Theme) {
+    let color = theme.snake;
+    let mut last_was_corner = false;
+
+    for (i, segment) in snake.segments.iter().enumerate() {
+        // Calculate the interpolated position and movement direction
+        let (draw_x, draw_y, direction) = calculate_segment_position(snake, i, ratio);
+
+        // Draw the segment based on whether the last segment was a corner
+        if last_was_corner {
+            draw_corner_piece(segment, direction, ratio, color);
+        } else {
+            draw_segment(draw_x, draw_y, direction, color);
+        }
+
+        // Check if this segment forms a corner piece
+        last_was_corner = i > 0 && i < snake.segments.len() - 1 && is_corner_piece(snake, i);
+
+        // Draw the current segment as a corner piece if needed
+        if last_was_corner {
+            let x = segment.cur.0 as f32 * CELL_SIZE;
+            let y = segment.cur.1 as f32 * CELL_SIZE;
+            draw_segment(x, y, direction, color);
+        }
+    }
+}
+
+pub fn calculate_segment_position(
+    snake: &Snake,
+    index: usize,
+    ratio: f32,
+) -> (f32, f32, (i32, i32)) {
+    let segment = &snake.segments[index];
+
+    if index == 0 {
+        // Head segment: interpolate between prev and cur positions
+        let prev = segment.prev;
+        let cur = segment.cur;
+        let x = (prev.0 as f32 * (1.0 - ratio) + cur.0 as f32 * ratio) * CELL_SIZE;
+        let y = (prev.1 as f32 * (1.0 - ratio) + cur.1 as f32 * ratio) * CELL_SIZE;
+        let dir = (cur.0 as i32 - prev.0 as i32, cur.1 as i32 - prev.1 as i32);
+
+        (x, y, dir)
+    } else {
+        // Body segments: interpolate toward the position of the segment ahead
+        let future_pos = snake.segments[index - 1].cur;
+        let x = (segment.cur.0 as f32 * (1.0 - ratio) + future_pos.0 as f32 * ratio) * CELL_SIZE;
+        let y = (segment.cur.1 as f32 * (1.0 - ratio) + future_pos.1 as f32 * ratio) * CELL_SIZE;
+        let dir = (
+            future_pos.0 as i32 - segment.cur.0 as i32,
+            future_pos.1 as i32 - segment.cur.1 as i32,
+        );
+
+        (x, y, dir)
+    }
+}
+
+fn is_corner_piece(snake: &Snake, index: usize) -> bool {
+    let prev_segment = &snake.segments[index - 1];
+    let next_segment = &snake.segments[index + 1];
+
+    // A corner is formed when previous and next segments have different directions
+    prev_segment.cur.0 != next_segment.cur.0 && prev_segment.cur.1 != next_segment.cur.1
+}
+
+fn draw_corner_piece(segment: &SnakeSegment, direction: (i32, i32), ratio: f32, color: Color) {
+    let (offset_x, offset_y, width, height) = match Direction::from_delta(direction) {
+        Some(Direction::Right) => (
+            CELL_GAP / 2. + (CELL_SIZE * ratio),
+            CELL_GAP / 2.0,
+            CELL_SIZE * 2. - CELL_GAP - (CELL_SIZE * ratio),
+            CELL_SIZE - CELL_GAP,
+        ),
+        Some(Direction::Left) => (
+            CELL_GAP / 2. - CELL_SIZE,
+            CELL_GAP / 2.0,
+            CELL_SIZE * 2. - CELL_GAP - (CELL_SIZE * ratio),
+            CELL_SIZE - CELL_GAP,
+        ),
+        Some(Direction::Down) => (
+            CELL_GAP / 2.0,
+            CELL_GAP / 2. + (CELL_SIZE * ratio),
+            CELL_SIZE - CELL_GAP,
+            CELL_SIZE * 2. - CELL_GAP - (CELL_SIZE * ratio),
+        ),
+        Some(Direction::Up) => (
+            CELL_GAP / 2.0,
+            CELL_GAP / 2. - CELL_SIZE,
+            CELL_SIZE - CELL_GAP,
+            CELL_SIZE * 2. - CELL_GAP - (CELL_SIZE * ratio),
+        ),
+        None => return,
+    };
+
+    draw_rectangle(
+        segment.cur.0 as f32 * CELL_SIZE + offset_x,
+        segment.cur.1 as f32 * CELL_SIZE + offset_y,
+        width,
+        height,
+        color,
+    );
+}
+
+fn draw_segment(x: f32, y: f32, direction: (i32, i32), color: Color) {
+    let (offset_x, offset_y, width, height) = match Direction::from_delta(direction) {
+        Some(Direction::Right) => (
+            CELL_GAP / 2.0,
+            CELL_GAP / 2.0,
+            CELL_SIZE,
+            CELL_SIZE - CELL_GAP,
+        ),
+        Some(Direction::Left) => (
+            -CELL_GAP / 2.0,
+            CELL_GAP / 2.0,
+            CELL_SIZE,
+            CELL_SIZE - CELL_GAP,
+        ),
+        Some(Direction::Down) => (
+            CELL_GAP / 2.0,
+            CELL_GAP / 2.0,
+            CELL_SIZE - CELL_GAP,
+            CELL_SIZE,
+        ),
+        Some(Direction::Up) => (
+            CELL_GAP / 2.0,
+            -CELL_GAP / 2.0,
+            CELL_SIZE - CELL_GAP,
+            CELL_SIZE,
+        ),
+        None => return,
+    };
+
+    draw_rectangle(x + offset_x, y + offset_y, width, height, color);
+}
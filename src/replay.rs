@@ -0,0 +1,183 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::direction::Direction;
+use crate::error::{FileError, SnakeError};
+use crate::files::{read_json, write_json};
+use crate::sim::Simulation;
+use crate::snake::Spawn;
+use crate::strategy::StrategyKind;
+
+// Where replays saved from the game end up, relative to the working directory
+const REPLAY_DIR: &str = "replays";
+
+/// Everything needed to play a game back exactly: the board it was played on
+/// and every move the snake made. Apples and the cycle come from the seed.
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub width: usize,
+    pub height: usize,
+    /// Where the snake started.
+    pub spawn: Spawn,
+    /// The bot that was selected when the replay was saved.
+    pub strategy: String,
+    /// Whether a player rather than the bot was steering then.
+    pub human: bool,
+    /// One letter per move: R, L, D or U.
+    pub moves: String,
+}
+
+impl Replay {
+    /// Captures the game so far, with `human` set when a player is steering.
+    pub fn record(sim: &Simulation, strategy: StrategyKind, human: bool) -> Self {
+        Replay {
+            seed: sim.seed(),
+            width: sim.width(),
+            height: sim.height(),
+            spawn: sim.spawn(),
+            strategy: strategy.name().to_owned(),
+            human,
+            // Bots and players only ever move one cell at a time, so every move
+            // has a letter
+            moves: sim
+                .moves()
+                .iter()
+                .filter_map(|&delta| Direction::from_delta(delta))
+                .map(Direction::letter)
+                .collect(),
+        }
+    }
+
+    /// Writes the replay as JSON into the replays directory and returns the path.
+    pub fn save(&self) -> Result<PathBuf, FileError> {
+        let file_name = format!("seed-{}-move-{}.json", self.seed, self.moves.len());
+        write_json(REPLAY_DIR, &file_name, self)
+    }
+
+    /// Reads a replay written by `save`.
+    pub fn load(path: &Path) -> Result<Self, FileError> {
+        let replay: Replay = read_json(path)?;
+
+        if let Some(letter) = replay
+            .moves
+            .chars()
+            .find(|&letter| Direction::from_letter(letter).is_none())
+        {
+            return Err(FileError::Invalid {
+                path: path.to_path_buf(),
+                message: format!("unknown move {:?}, moves must be R, L, D or U", letter),
+            });
+        }
+
+        Ok(replay)
+    }
+}
+
+/// Plays a replay back into a simulation, a move at a time or by jumping to
+/// any move in it.
+pub struct Playback {
+    replay: Replay,
+    moves: Vec<(i32, i32)>,
+    // How many moves have been played
+    position: usize,
+    pub paused: bool,
+}
+
+impl Playback {
+    /// Starts playback from the first move, along with the simulation to play into.
+    pub fn new(replay: Replay) -> Result<(Self, Simulation), SnakeError> {
+        let sim = Simulation::with_spawn(replay.width, replay.height, replay.seed, replay.spawn)?;
+        let moves = replay
+            .moves
+            .chars()
+            .filter_map(Direction::from_letter)
+            .map(Direction::delta)
+            .collect();
+        let playback = Playback {
+            replay,
+            moves,
+            position: 0,
+            paused: false,
+        };
+        Ok((playback, sim))
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    // Play the next move, if there is one
+    pub fn advance(&mut self, sim: &mut Simulation) {
+        if let Some(&direction) = self.moves.get(self.position) {
+            sim.step_towards(direction);
+            self.position += 1;
+        }
+    }
+
+    // Jump to just after move `target`. Going backwards replays from the start,
+    // which the seed makes exact
+    pub fn seek(&mut self, sim: &mut Simulation, target: usize) -> Result<(), SnakeError> {
+        let target = target.min(self.moves.len());
+        if target < self.position {
+            *sim = Simulation::with_spawn(
+                self.replay.width,
+                self.replay.height,
+                self.replay.seed,
+                self.replay.spawn,
+            )?;
+            self.position = 0;
+        }
+        while self.position < target {
+            self.advance(sim);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::BotParams;
+
+    // The whole simulation as it would be saved, to compare states exactly
+    fn state(sim: &Simulation) -> String {
+        serde_json::to_string(sim).unwrap()
+    }
+
+    #[test]
+    fn seeking_either_way_matches_the_recorded_game() {
+        let mut sim = Simulation::new(10, 10, 3).unwrap();
+        let mut strategy = StrategyKind::HamiltonianShortcut.build(&BotParams::default());
+        let mut states = vec![state(&sim)];
+        while !sim.is_over() && states.len() <= 300 {
+            sim.step(strategy.as_mut());
+            states.push(state(&sim));
+        }
+
+        let replay = Replay::record(&sim, StrategyKind::HamiltonianShortcut, false);
+        assert_eq!(replay.moves.len(), states.len() - 1);
+        let (mut playback, mut played) = Playback::new(replay).unwrap();
+        assert_eq!(state(&played), states[0]);
+
+        for target in [200, 50, 0, 299, 120] {
+            playback.seek(&mut played, target).unwrap();
+            assert_eq!(playback.position(), target);
+            assert_eq!(
+                state(&played),
+                states[target],
+                "after seeking to move {}",
+                target
+            );
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// Small seedable PCG32 generator owned by each game, so a seed fully
+/// determines the cycle and every apple that spawns.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Rng {
+    state: u64,
+}
+
+const INCREMENT: u64 = 1442695040888963407;
+const MULTIPLIER: u64 = 6364136223846793005;
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng { state: 0 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old_state = self.state;
+        self.state = old_state.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT);
+        let xorshifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        let rot = (old_state >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    // Returns a value in low..high
+    pub fn gen_range(&mut self, low: usize, high: usize) -> usize {
+        let r = self.next_u32() as f64 / (u32::MAX as f64 + 1.0);
+        low + ((high - low) as f64 * r) as usize
+    }
+}
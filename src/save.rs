@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::bot::NOT_ON_CYCLE;
+use crate::error::FileError;
+use crate::files::{read_json, write_json};
+use crate::params::BotParams;
+use crate::sim::Simulation;
+use crate::strategy::{Strategy, StrategyKind};
+
+// Where games saved with the hotkey end up, relative to the working directory
+const SAVE_DIR: &str = "saves";
+
+/// A game frozen mid-play: the whole simulation plus the controls around it,
+/// so it carries on exactly where it was saved.
+#[derive(Serialize, Deserialize)]
+pub struct SavedGame {
+    pub sim: Simulation,
+    /// The seed new games start from after a restart, if one was fixed.
+    pub fixed_seed: Option<u64>,
+    pub human: bool,
+    pub strategy: String,
+    /// Whatever the strategy had built up, such as a repaired cycle.
+    pub strategy_state: Option<serde_json::Value>,
+    pub params: BotParams,
+    pub speed_multiplier: f32,
+    pub step_timer: f32,
+    pub play_time: f32,
+    pub draw_cycle: bool,
+}
+
+impl SavedGame {
+    /// Writes the game as JSON into the saves directory and returns the path.
+    pub fn save(&self) -> Result<PathBuf, FileError> {
+        let file_name = format!(
+            "seed-{}-move-{}.json",
+            self.sim.seed(),
+            self.sim.moves().len()
+        );
+        write_json(SAVE_DIR, &file_name, self)
+    }
+
+    /// Reads a game written by `save`, checking it can be played on from.
+    pub fn load(path: &Path) -> Result<Self, FileError> {
+        let saved: SavedGame = read_json(path)?;
+        let invalid = |message: String| FileError::Invalid {
+            path: path.to_path_buf(),
+            message,
+        };
+
+        saved.sim.validate().map_err(invalid)?;
+        StrategyKind::parse(&saved.strategy).map_err(invalid)?;
+        let strategy = saved.restore_strategy().map_err(invalid)?;
+        // A bot's own cycle, like a repaired one, has to cover the same cells
+        // as the board's
+        if let Some(cycle) = strategy.cycle() {
+            let view = saved.sim.view();
+            let on_board_cycle = |&(x, y): &(usize, usize)| {
+                x < view.width
+                    && y < view.height
+                    && view.tour_numbers[x + y * view.width] != NOT_ON_CYCLE
+            };
+            if cycle.len() != view.cycle.len() || !cycle.iter().all(on_board_cycle) {
+                return Err(invalid(format!(
+                    "the {} bot's cycle does not fit the {}x{} board",
+                    saved.strategy, view.width, view.height
+                )));
+            }
+        }
+
+        Ok(saved)
+    }
+
+    pub fn strategy_kind(&self) -> StrategyKind {
+        StrategyKind::from_name(&self.strategy).unwrap_or(StrategyKind::HamiltonianShortcut)
+    }
+
+    /// Rebuilds the strategy as it was when the game was saved.
+    pub fn restore_strategy(&self) -> Result<Box<dyn Strategy>, String> {
+        let mut strategy = self.strategy_kind().build(&self.params);
+        if let Some(state) = &self.strategy_state {
+            strategy.load_state(state.clone())?;
+        }
+        Ok(strategy)
+    }
+}
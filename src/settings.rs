@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::FileError;
+use crate::files::{read_toml, write_toml};
+use crate::game::ResizeMode;
+use crate::options::{MAX_CELL_SIZE, MAX_SPEED, MIN_CELL_SIZE, MIN_SPEED};
+use crate::overlay::Overlay;
+use crate::strategy::StrategyKind;
+use crate::theme::Theme;
+
+/// The player's preferences, kept between runs in `settings.toml` in the user
+/// config directory. Command-line options win over anything set here, and
+/// changes made through the controls panel are written back.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Board size in cells. Without one the board fills the default window.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<usize>,
+    pub cell_size: f32,
+    pub speed: f32,
+    /// The bot selected at start, by the name shown in the UI.
+    pub strategy: String,
+    pub show_cycle: bool,
+    pub overlay: Overlay,
+    pub show_decision: bool,
+    pub resize: ResizeMode,
+    /// The theme in use, built-in or one of `themes`.
+    pub theme: String,
+    /// Themes of the player's own, by name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub themes: BTreeMap<String, Theme>,
+    /// Draw the snake from the sprite atlas in `assets` rather than as shapes.
+    pub sprites: bool,
+    pub assets: PathBuf,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            width: None,
+            height: None,
+            cell_size: 25.0,
+            speed: 5.0,
+            strategy: StrategyKind::HamiltonianShortcut.name().to_owned(),
+            show_cycle: false,
+            overlay: Overlay::Off,
+            show_decision: false,
+            resize: ResizeMode::Scale,
+            theme: "classic".to_owned(),
+            themes: BTreeMap::new(),
+            sprites: false,
+            assets: PathBuf::from("assets"),
+        }
+    }
+}
+
+impl Settings {
+    /// Where the settings live, if the platform has a config directory.
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("snake").join("settings.toml"))
+    }
+
+    /// Reads the settings file, or the defaults when there is none yet.
+    pub fn load() -> Result<Self, FileError> {
+        let Some(path) = Self::path().filter(|path| path.exists()) else {
+            return Ok(Settings::default());
+        };
+        let settings: Settings = read_toml(&path)?;
+
+        let invalid = |message: String| FileError::Invalid {
+            path: path.clone(),
+            message,
+        };
+        if !(MIN_CELL_SIZE..=MAX_CELL_SIZE).contains(&settings.cell_size) {
+            return Err(invalid(format!(
+                "cell_size must be from {} to {} pixels",
+                MIN_CELL_SIZE, MAX_CELL_SIZE
+            )));
+        }
+        if !(MIN_SPEED..=MAX_SPEED).contains(&settings.speed) {
+            return Err(invalid(format!(
+                "speed must be from {} to {}",
+                MIN_SPEED, MAX_SPEED
+            )));
+        }
+        StrategyKind::parse(&settings.strategy).map_err(invalid)?;
+        if Theme::find(&settings.theme, &settings.themes).is_none() {
+            return Err(invalid(format!(
+                "unknown theme {:?}, expected one of: {}",
+                settings.theme,
+                Theme::names(&settings.themes).join(", ")
+            )));
+        }
+        if settings.width.is_some() != settings.height.is_some() {
+            return Err(invalid("width and height must be set together".to_owned()));
+        }
+
+        Ok(settings)
+    }
+
+    /// Writes the settings back to the settings file.
+    pub fn save(&self) -> Result<(), FileError> {
+        match Self::path() {
+            Some(path) => write_toml(&path, self),
+            // Nowhere to keep them, so they last as long as the window
+            None => Ok(()),
+        }
+    }
+
+    /// The bot selected at start.
+    pub fn strategy_kind(&self) -> StrategyKind {
+        // `load` has checked the name
+        StrategyKind::from_name(&self.strategy).unwrap_or(StrategyKind::HamiltonianShortcut)
+    }
+
+    /// The theme in use.
+    pub fn theme(&self) -> Theme {
+        // `load` has checked the name
+        Theme::find(&self.theme, &self.themes).unwrap_or_default()
+    }
+}
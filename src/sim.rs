@@ -0,0 +1,443 @@
+use serde::{Deserialize, Serialize};
+
+use crate::bot::{check_cycle, generate_hamiltonian_cycle, skipped_cell};
+use crate::direction::Direction;
+use crate::error::SnakeError;
+use crate::rng::Rng;
+use crate::snake::{DeathCause, Snake, SnakeSegment, Spawn};
+use crate::strategy::{BoardView, Strategy};
+
+/// How a finished game ended.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Outcome {
+    /// The snake filled the board.
+    Won,
+    Died(DeathCause),
+}
+
+/// Headless game state: the snake, the apple and the cycle the bot follows on a
+/// board of an explicit size. Nothing in here touches the window, so a game can
+/// be played out in tests or batch runs as well as behind `Game`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Simulation {
+    width: usize,
+    height: usize,
+    seed: u64,
+    spawn: Spawn,
+    rng: Rng,
+    score: u32,
+    // Set once the game has ended
+    outcome: Option<Outcome>,
+    snake: Snake,
+    open_cells: Vec<(usize, usize)>,
+    apple: (usize, usize),
+    cycle: Vec<(usize, usize)>,
+    tour_numbers: Vec<usize>,
+    skipped_cell: Option<(usize, usize)>,
+    // Every direction the snake has moved in, for replays
+    moves: Vec<(i32, i32)>,
+    // The fewest moves that could have eaten every apple eaten so far: the sum
+    // of the distances from the head to each apple as it spawned
+    shortest_moves: u64,
+    // The distance from the head to the current apple as it spawned, counted
+    // into `shortest_moves` once the apple is eaten
+    apple_distance: u64,
+}
+
+/// What one move changed, enough for `undo` to take it back. It holds only
+/// what the move touched, so it stays the same few dozen bytes however large
+/// the board is or however long the game has run.
+pub struct Undo {
+    rng: Rng,
+    score: u32,
+    outcome: Option<Outcome>,
+    apple: (usize, usize),
+    shortest_moves: u64,
+    apple_distance: u64,
+    // The snake's heading and last segment before the move
+    direction: (i32, i32),
+    tail: SnakeSegment,
+    grew: bool,
+    // Whether the tail's cell was added to the end of `open_cells`, and where
+    // in it the head's cell was taken from, so the order apples are drawn
+    // from comes back exactly
+    freed_tail: bool,
+    taken: Option<usize>,
+}
+
+impl Simulation {
+    /// Creates a game whose cycle and apples are all drawn from `seed`, with the
+    /// snake in its default starting place.
+    #[cfg(test)]
+    pub fn new(width: usize, height: usize, seed: u64) -> Result<Self, SnakeError> {
+        Self::with_spawn(width, height, seed, Spawn::default())
+    }
+
+    /// Creates a game with the snake starting at `spawn`.
+    pub fn with_spawn(
+        width: usize,
+        height: usize,
+        seed: u64,
+        spawn: Spawn,
+    ) -> Result<Self, SnakeError> {
+        let mut rng = Rng::new(seed);
+        let snake = Snake::spawn_on_map(spawn.x, spawn.y, spawn.length, width, height)?;
+        // On a board with two odd sides the cycle leaves one cell out; it never
+        // holds an apple, and the board is won once every other cell is filled
+        let skipped_cell = skipped_cell(width as i32, height as i32);
+        if skipped_cell == Some((spawn.x, spawn.y)) {
+            return Err(SnakeError::SpawnOffCycle {
+                x: spawn.x,
+                y: spawn.y,
+            });
+        }
+
+        // open_cells should be a vector of tuples (x, y) representing the available cells for the apple i.e entire map - snake cells
+        let mut open_cells = Vec::new();
+        for x in 0..width {
+            for y in 0..height {
+                if !snake.segments.iter().any(|s| s.cur == (x, y)) && skipped_cell != Some((x, y)) {
+                    open_cells.push((x, y));
+                }
+            }
+        }
+
+        if open_cells.is_empty() {
+            return Err(SnakeError::NoRoomForApple);
+        }
+
+        let apple = Self::spawn_apple(&mut rng, &open_cells);
+        let apple_distance = distance(snake.segments[0].cur, apple);
+        let (cycle, tour_numbers) =
+            generate_hamiltonian_cycle(width as i32, height as i32, &mut rng)?;
+
+        Ok(Simulation {
+            width,
+            height,
+            seed,
+            spawn,
+            rng,
+            score: 0,
+            outcome: None,
+            snake,
+            open_cells,
+            apple,
+            cycle,
+            tour_numbers,
+            skipped_cell,
+            moves: Vec::new(),
+            shortest_moves: 0,
+            apple_distance,
+        })
+    }
+
+    /// Checks a simulation read back from a save file hangs together, so a
+    /// hand-edited or truncated save cannot crash the game later on.
+    pub fn validate(&self) -> Result<(), String> {
+        let in_bounds = |&(x, y): &(usize, usize)| x < self.width && y < self.height;
+
+        if self.width < 2 || self.height < 2 {
+            return Err(format!(
+                "a {}x{} board is too small",
+                self.width, self.height
+            ));
+        }
+        check_cycle(&self.cycle, &self.tour_numbers, self.width, self.height)?;
+        if self.snake.segments.is_empty() {
+            return Err("the snake has no segments".to_owned());
+        }
+        if Direction::from_delta(self.snake.direction()).is_none() {
+            return Err(format!(
+                "the snake is heading {:?}, which is not one cell in any direction",
+                self.snake.direction()
+            ));
+        }
+        // The head of a dead snake can be off the board
+        let body = if self.is_over() {
+            &self.snake.segments[1..]
+        } else {
+            &self.snake.segments[..]
+        };
+        if let Some(segment) = body.iter().find(|segment| !in_bounds(&segment.cur)) {
+            return Err(format!("snake segment {:?} is off the board", segment.cur));
+        }
+        if !in_bounds(&self.apple) || !self.open_cells.iter().all(in_bounds) {
+            return Err("the apple or an open cell is off the board".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Advances the game by one move of the snake, steered by a bot strategy.
+    pub fn step(&mut self, strategy: &mut dyn Strategy) {
+        let direction = strategy.next_direction(&self.view());
+        self.step_towards(direction);
+    }
+
+    /// Advances the game by one move of the snake in the given direction, and
+    /// returns what it changed so it can be undone. Does nothing once the game
+    /// is over.
+    pub fn step_towards(&mut self, direction: (i32, i32)) -> Option<Undo> {
+        if self.is_over() {
+            return None;
+        }
+        let mut undo = Undo {
+            rng: self.rng.clone(),
+            score: self.score,
+            outcome: self.outcome,
+            apple: self.apple,
+            shortest_moves: self.shortest_moves,
+            apple_distance: self.apple_distance,
+            direction: self.snake.direction(),
+            tail: self.snake.segments[self.snake.segments.len() - 1].clone(),
+            grew: false,
+            freed_tail: false,
+            taken: None,
+        };
+
+        self.moves.push(direction);
+        let (head, tail) = self.snake.step(direction);
+        let eating = self.snake.is_eating(self.apple);
+        // The tail's cell frees up unless the snake grows back into it. Free it
+        // before taking the head's cell, since the head can move straight into
+        // the cell the tail just left
+        if !eating && self.skipped_cell != Some(tail) {
+            self.open_cells.push(tail);
+            undo.freed_tail = true;
+        }
+        undo.taken = self.open_cells.iter().position(|&cell| cell == head);
+        if let Some(index) = undo.taken {
+            self.open_cells.remove(index);
+        }
+
+        if eating {
+            self.score += 1;
+            self.shortest_moves += self.apple_distance;
+            self.snake.grow();
+            undo.grew = true;
+
+            // Spawn a new apple
+            if !self.open_cells.is_empty() {
+                self.apple = Self::spawn_apple(&mut self.rng, &self.open_cells);
+                self.apple_distance = distance(head, self.apple);
+            } else {
+                // No more open cells, the board is full
+                self.outcome = Some(Outcome::Won);
+            }
+        }
+
+        self.check_for_death();
+        Some(undo)
+    }
+
+    /// Takes back the move `undo` was returned for. Moves have to be taken
+    /// back newest first.
+    pub fn undo(&mut self, undo: Undo) {
+        let head = self.snake.segments[0].cur;
+        if let Some(index) = undo.taken {
+            self.open_cells.insert(index, head);
+        }
+        if undo.freed_tail {
+            self.open_cells.pop();
+        }
+        self.snake.unstep(undo.direction, undo.tail, undo.grew);
+        self.moves.pop();
+        if self.moves.is_empty() {
+            // Back at the start, where the snake has not come from anywhere
+            for segment in &mut self.snake.segments {
+                segment.prev = segment.cur;
+            }
+        }
+
+        self.rng = undo.rng;
+        self.score = undo.score;
+        self.outcome = undo.outcome;
+        self.apple = undo.apple;
+        self.shortest_moves = undo.shortest_moves;
+        self.apple_distance = undo.apple_distance;
+    }
+
+    fn check_for_death(&mut self) {
+        if let Some(cause) = self.snake.collision(self.width, self.height) {
+            self.outcome = Some(Outcome::Died(cause));
+        }
+    }
+
+    fn spawn_apple(rng: &mut Rng, open_cells: &[(usize, usize)]) -> (usize, usize) {
+        let apple_index = rng.gen_range(0, open_cells.len());
+        open_cells[apple_index]
+    }
+
+    /// A read-only view of the board for strategies to decide on.
+    pub fn view(&self) -> BoardView<'_> {
+        BoardView {
+            width: self.width,
+            height: self.height,
+            snake: &self.snake,
+            apple: self.apple,
+            cycle: &self.cycle,
+            tour_numbers: &self.tour_numbers,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn spawn(&self) -> Spawn {
+        self.spawn
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    /// How the game ended, or `None` while it is still going.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    /// How close the snake came to the fewest possible moves, from 0 to 1: the
+    /// shortest distance to each apple it ate over the moves it made.
+    pub fn efficiency(&self) -> f64 {
+        self.shortest_moves as f64 / self.moves.len().max(1) as f64
+    }
+
+    pub fn snake(&self) -> &Snake {
+        &self.snake
+    }
+
+    pub fn apple(&self) -> (usize, usize) {
+        self.apple
+    }
+
+    pub fn cycle(&self) -> &[(usize, usize)] {
+        &self.cycle
+    }
+
+    pub fn skipped_cell(&self) -> Option<(usize, usize)> {
+        self.skipped_cell
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The directions of every move so far, oldest first.
+    pub fn moves(&self) -> &[(i32, i32)] {
+        &self.moves
+    }
+}
+
+// Moves needed to get from one cell to another with nothing in the way
+fn distance(from: (usize, usize), to: (usize, usize)) -> u64 {
+    (from.0.abs_diff(to.0) + from.1.abs_diff(to.1)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::NOT_ON_CYCLE;
+    use crate::params::BotParams;
+    use crate::strategy::StrategyKind;
+
+    // The whole simulation as it would be saved, to compare states exactly
+    fn state(sim: &Simulation) -> String {
+        serde_json::to_string(sim).unwrap()
+    }
+
+    // Plays a game out with the default bot
+    fn play(width: usize, height: usize, seed: u64) -> Simulation {
+        let mut sim = Simulation::new(width, height, seed).unwrap();
+        let mut strategy = StrategyKind::HamiltonianShortcut.build(&BotParams::default());
+        while !sim.is_over() {
+            sim.step(strategy.as_mut());
+        }
+        sim
+    }
+
+    #[test]
+    fn bot_wins_a_seeded_game() {
+        let sim = play(20, 20, 0);
+        assert_eq!(sim.outcome(), Some(Outcome::Won));
+        assert_eq!(sim.snake().segments.len(), 20 * 20);
+    }
+
+    #[test]
+    fn same_seed_plays_the_same_game() {
+        assert_eq!(state(&play(20, 20, 7)), state(&play(20, 20, 7)));
+    }
+
+    #[test]
+    fn validate_rejects_tour_numbers_off_the_cycle() {
+        let mut sim = Simulation::new(7, 7, 0).unwrap();
+        assert_eq!(sim.validate(), Ok(()));
+
+        // Ahead of the head by more than the whole cycle
+        let skipped = 6 + 6 * 7;
+        sim.tour_numbers[skipped] = sim.cycle.len() + 3;
+        assert!(sim.validate().is_err());
+
+        // On the cycle, but at another cell's place along it
+        sim.tour_numbers[skipped] = sim.tour_numbers[0];
+        assert!(sim.validate().is_err());
+    }
+
+    #[test]
+    fn validate_rejects_a_cycle_that_is_not_a_tour() {
+        let sim = Simulation::new(10, 10, 0).unwrap();
+
+        // Two cells that agree with their tour numbers, but skip the rest of
+        // the board and jump across it
+        let mut broken = sim.clone();
+        broken.tour_numbers = vec![NOT_ON_CYCLE; 100];
+        broken.cycle = vec![(0, 0), (9, 9)];
+        broken.tour_numbers[0] = 0;
+        broken.tour_numbers[99] = 1;
+        assert!(broken.validate().is_err());
+
+        // Every cell, but not in steps of one
+        let mut broken = sim.clone();
+        broken.cycle.swap(10, 50);
+        let cells = [broken.cycle[10], broken.cycle[50]];
+        for (number, (x, y)) in [(10, cells[0]), (50, cells[1])] {
+            broken.tour_numbers[x + y * 10] = number;
+        }
+        assert!(broken.validate().is_err());
+
+        // Heading two cells at a time
+        let mut json = serde_json::to_value(&sim).unwrap();
+        json["snake"]["direction"] = serde_json::json!([2, 0]);
+        let broken: Simulation = serde_json::from_value(json).unwrap();
+        assert!(broken.validate().is_err());
+    }
+
+    #[test]
+    fn undo_takes_moves_back_exactly() {
+        let mut sim = Simulation::new(10, 10, 3).unwrap();
+        let mut strategy = StrategyKind::HamiltonianShortcut.build(&BotParams::default());
+        let mut states = vec![state(&sim)];
+        let mut undos = Vec::new();
+        // Long enough to eat apples and for the head to follow its tail closely
+        while !sim.is_over() && undos.len() < 600 {
+            let direction = strategy.next_direction(&sim.view());
+            undos.push(sim.step_towards(direction).unwrap());
+            states.push(state(&sim));
+        }
+        assert!(sim.score() > 10);
+
+        // Back to the start, checking every state on the way
+        states.pop();
+        while let Some(undo) = undos.pop() {
+            sim.undo(undo);
+            assert_eq!(state(&sim), states.pop().unwrap());
+        }
+    }
+}
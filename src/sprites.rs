@@ -0,0 +1,145 @@
+use std::f32::consts::FRAC_PI_2;
+use std::path::Path;
+
+use macroquad::prelude::*;
+
+use crate::error::FileError;
+use crate::render::{calculate_segment_position, CELL_SIZE};
+use crate::snake::Snake;
+
+/// The atlas the sprite renderer looks for in the asset directory.
+pub const ATLAS_FILE: &str = "snake.png";
+
+// The tiles in the atlas, left to right
+#[derive(Clone, Copy)]
+enum Tile {
+    // Facing right, joining the body on its left edge
+    Head,
+    // Running from the left edge to the right edge
+    Straight,
+    // Joining the left and bottom edges
+    Corner,
+    // Joining the body on its right edge
+    Tail,
+}
+
+/// Draws the snake with textures instead of rectangles. The atlas is one image
+/// of four square tiles side by side: head, straight, corner and tail, each
+/// drawn as the comments on `Tile` describe and turned to fit.
+pub struct SpriteAtlas {
+    texture: Texture2D,
+    tile_size: f32,
+}
+
+impl SpriteAtlas {
+    /// Loads `snake.png` from `dir`. Needs the window to be open.
+    pub fn load(dir: &Path) -> Result<Self, FileError> {
+        let path = dir.join(ATLAS_FILE);
+        let bytes = std::fs::read(&path).map_err(|err| FileError::Read {
+            path: path.clone(),
+            message: err.to_string(),
+        })?;
+        let image = Image::from_file_with_format(&bytes, None).map_err(|err| FileError::Parse {
+            path: path.clone(),
+            message: err.to_string(),
+        })?;
+        if image.height == 0 || image.width != image.height * 4 {
+            return Err(FileError::Invalid {
+                path,
+                message: format!(
+                    "expected four square tiles side by side (head, straight, corner, tail), \
+                     got a {}x{} image",
+                    image.width, image.height
+                ),
+            });
+        }
+
+        Ok(SpriteAtlas {
+            texture: Texture2D::from_image(&image),
+            tile_size: image.height as f32,
+        })
+    }
+
+    /// Draws the snake `ratio` of the way through its last move. Only the head
+    /// and tail move; every cell between them stays covered for the whole
+    /// move, so the body is drawn still and turns corners without gaps.
+    pub fn draw_snake(&self, snake: &Snake, ratio: f32) {
+        let segments = &snake.segments;
+        let last = segments.len() - 1;
+
+        for i in 1..last {
+            let cell = segments[i].cur;
+            let ahead = offset(cell, segments[i - 1].cur);
+            let behind = offset(cell, segments[i + 1].cur);
+            let (tile, rotation) = if ahead == (-behind.0, -behind.1) {
+                (Tile::Straight, angle(ahead))
+            } else {
+                (Tile::Corner, corner_rotation(ahead, behind))
+            };
+            self.draw_tile(
+                tile,
+                cell.0 as f32 * CELL_SIZE,
+                cell.1 as f32 * CELL_SIZE,
+                rotation,
+            );
+        }
+
+        if last > 0 {
+            let (x, y, direction) = calculate_segment_position(snake, last, ratio);
+            self.draw_tile(Tile::Tail, x, y, angle(direction));
+        }
+
+        let (x, y, direction) = calculate_segment_position(snake, 0, ratio);
+        // A snake that has not moved yet faces the way it is about to go
+        let direction = if direction == (0, 0) {
+            snake.direction()
+        } else {
+            direction
+        };
+        self.draw_tile(Tile::Head, x, y, angle(direction));
+    }
+
+    fn draw_tile(&self, tile: Tile, x: f32, y: f32, rotation: f32) {
+        draw_texture_ex(
+            &self.texture,
+            x,
+            y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(vec2(CELL_SIZE, CELL_SIZE)),
+                source: Some(Rect::new(
+                    tile as usize as f32 * self.tile_size,
+                    0.0,
+                    self.tile_size,
+                    self.tile_size,
+                )),
+                rotation,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+// The step from one cell to a neighbouring one. Cells off the board wrap
+// around in usize, which the casts undo
+fn offset(from: (usize, usize), to: (usize, usize)) -> (i32, i32) {
+    (to.0 as i32 - from.0 as i32, to.1 as i32 - from.1 as i32)
+}
+
+// Clockwise turn from facing right to facing `direction`, with y pointing down
+fn angle((dx, dy): (i32, i32)) -> f32 {
+    (dy as f32).atan2(dx as f32)
+}
+
+// Quarter turns that bring the corner tile's left and bottom edges round to
+// face the two neighbours
+fn corner_rotation(a: (i32, i32), b: (i32, i32)) -> f32 {
+    let mut edges = [(-1, 0), (0, 1)];
+    for turns in 0..4 {
+        if edges.contains(&a) && edges.contains(&b) {
+            return turns as f32 * FRAC_PI_2;
+        }
+        edges = edges.map(|(x, y)| (-y, x));
+    }
+    0.0
+}
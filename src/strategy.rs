@@ -0,0 +1,188 @@
+mod greedy;
+mod hamiltonian;
+mod repair;
+
+pub use greedy::GreedyPathfinder;
+pub use hamiltonian::HamiltonianShortcut;
+pub use repair::CycleRepair;
+
+use std::any::Any;
+
+use crate::params::BotParams;
+use crate::snake::Snake;
+
+/// Read-only view of the board handed to a strategy before every move.
+pub struct BoardView<'a> {
+    pub width: usize,
+    pub height: usize,
+    pub snake: &'a Snake,
+    pub apple: (usize, usize),
+    /// The game's Hamiltonian cycle, in tour order.
+    pub cycle: &'a [(usize, usize)],
+    /// Each cell's position along `cycle`, indexed by `x + y * width`. Cells
+    /// off the cycle are numbered `NOT_ON_CYCLE`.
+    pub tour_numbers: &'a [usize],
+}
+
+/// Why a bot picked the move it did: the numbers it weighed up, the cells it
+/// considered and which branch of its logic made the call.
+#[derive(Debug)]
+pub struct Decision {
+    /// Where the head was when the decision was made.
+    pub head: (usize, usize),
+    /// How far the bot was willing to cut across the cycle, if it got as far as
+    /// working that out.
+    pub budget: Option<Budget>,
+    /// The neighbouring cells tried for a shortcut, in evaluation order.
+    pub candidates: Vec<Candidate>,
+    pub direction: (i32, i32),
+    pub branch: Branch,
+}
+
+/// The distances along the cycle behind a shortcut decision.
+#[derive(Debug)]
+pub struct Budget {
+    pub distance_to_food: usize,
+    pub distance_to_tail: usize,
+    /// The furthest along the cycle a shortcut may jump.
+    pub cutting_amount_available: usize,
+}
+
+/// One neighbour of the head, considered as a shortcut.
+#[derive(Debug)]
+pub struct Candidate {
+    pub direction: (i32, i32),
+    pub cell: (usize, usize),
+    /// The snake or a wall is in the way.
+    pub blocked: bool,
+    /// How far along the cycle the cell is from the head, the
+    /// `dist_on_cycle_to_next` compared against the budget. `None` when the
+    /// cell is blocked or off the cycle.
+    pub dist_on_cycle: Option<usize>,
+}
+
+/// Which part of the bot's logic picked the move.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Branch {
+    /// The furthest candidate within budget.
+    Shortcut,
+    /// No candidate was within budget, so the next cell on the tour.
+    FollowTour,
+    /// The tour numbers were no help, so the next cell found by position.
+    FollowCycle,
+    /// Off the cycle entirely, so any move that does not collide.
+    AnySafeMove,
+    /// Every move collides.
+    Trapped,
+}
+
+impl Branch {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Branch::Shortcut => "shortcut",
+            Branch::FollowTour => "follow tour",
+            Branch::FollowCycle => "follow cycle",
+            Branch::AnySafeMove => "any safe move",
+            Branch::Trapped => "trapped",
+        }
+    }
+}
+
+/// Decides which way the snake moves next. Implement this to plug a new bot
+/// into the game; strategies are free to keep state between moves.
+pub trait Strategy {
+    /// Returns the direction of the next move as a unit `(dx, dy)` step.
+    fn next_direction(&mut self, view: &BoardView) -> (i32, i32);
+
+    /// Like `next_direction`, but also explains the choice, for the decision
+    /// overlay. Strategies that keep no such record return `None` alongside.
+    fn decide(&mut self, view: &BoardView) -> ((i32, i32), Option<Decision>) {
+        (self.next_direction(view), None)
+    }
+
+    /// Picks up new tuning parameters mid-game. Strategies without any
+    /// tunable behaviour can ignore this.
+    fn set_params(&mut self, _params: &BotParams) {}
+
+    /// Anything the strategy has built up over the game, for save files.
+    /// Stateless strategies have nothing to save.
+    fn save_state(&self) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// Restores what `save_state` returned, or explains why it cannot.
+    fn load_state(&mut self, _state: serde_json::Value) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Anything the strategy has built up, kept in memory for stepping back
+    /// through moves. Taken before every move, so it should be cheap.
+    fn checkpoint(&self) -> Option<Box<dyn Any>> {
+        None
+    }
+
+    /// Goes back to what `checkpoint` returned.
+    fn rewind(&mut self, _checkpoint: Box<dyn Any>) {}
+
+    /// The cycle the strategy is currently following, if it keeps its own
+    /// rather than using the game's.
+    fn cycle(&self) -> Option<&[(usize, usize)]> {
+        None
+    }
+}
+
+/// The built-in strategies, selectable at runtime.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum StrategyKind {
+    HamiltonianShortcut,
+    GreedyPathfinder,
+    CycleRepair,
+}
+
+impl StrategyKind {
+    pub const ALL: [StrategyKind; 3] = [
+        StrategyKind::HamiltonianShortcut,
+        StrategyKind::GreedyPathfinder,
+        StrategyKind::CycleRepair,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StrategyKind::HamiltonianShortcut => "hamiltonian",
+            StrategyKind::GreedyPathfinder => "greedy",
+            StrategyKind::CycleRepair => "repair",
+        }
+    }
+
+    /// Looks a strategy up by the name shown in the UI.
+    pub fn from_name(name: &str) -> Option<StrategyKind> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    /// Like `from_name`, but names the strategies there are when there is no
+    /// such one.
+    pub fn parse(name: &str) -> Result<StrategyKind, String> {
+        Self::from_name(name).ok_or_else(|| {
+            format!(
+                "unknown strategy {:?}, expected one of: {}",
+                name,
+                Self::ALL.map(StrategyKind::name).join(", ")
+            )
+        })
+    }
+
+    // The strategy after this one, wrapping around
+    pub fn next(self) -> StrategyKind {
+        let index = Self::ALL.iter().position(|&kind| kind == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Creates a fresh instance of the strategy, tuned with `params`.
+    pub fn build(self, params: &BotParams) -> Box<dyn Strategy> {
+        match self {
+            StrategyKind::HamiltonianShortcut => Box::new(HamiltonianShortcut::new(params)),
+            StrategyKind::GreedyPathfinder => Box::new(GreedyPathfinder),
+            StrategyKind::CycleRepair => Box::new(CycleRepair::new(params)),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::VecDeque;
+
+use crate::direction::Direction;
+use crate::strategy::{BoardView, Strategy};
+
+/// Heads straight for the apple along the shortest free path, but only when the
+/// snake could still reach its own tail after eating. Otherwise it chases its
+/// tail, taking the longest safe route so the board has time to open up.
+pub struct GreedyPathfinder;
+
+impl Strategy for GreedyPathfinder {
+    fn next_direction(&mut self, view: &BoardView) -> (i32, i32) {
+        let (width, height) = (view.width, view.height);
+        let body: Vec<(usize, usize)> = view.snake.segments.iter().map(|s| s.cur).collect();
+        let head = body[0];
+
+        if let Some(path) = shortest_path(width, height, &body, view.apple) {
+            // Play the path out and check the grown snake is not boxed in
+            let mut body_after: Vec<(usize, usize)> = path.iter().rev().copied().collect();
+            body_after.extend_from_slice(&body);
+            body_after.truncate(body.len() + 1);
+
+            let tail_after = body_after[body_after.len() - 1];
+            if shortest_path(width, height, &body_after, tail_after).is_some() {
+                return direction_between(head, path[0]);
+            }
+        }
+
+        self.chase_tail(width, height, &body)
+            .unwrap_or_else(|| view.snake.direction())
+    }
+}
+
+impl GreedyPathfinder {
+    // Pick the free neighbour that keeps the tail reachable and is furthest from it
+    fn chase_tail(
+        &self,
+        width: usize,
+        height: usize,
+        body: &[(usize, usize)],
+    ) -> Option<(i32, i32)> {
+        let head = body[0];
+        let blocked = occupancy(width, height, body);
+
+        let mut best: Option<((i32, i32), usize)> = None;
+        let mut any_free = None;
+
+        for next in neighbours(width, height, head) {
+            if blocked[next.0 + next.1 * width] {
+                continue;
+            }
+            let dir = direction_between(head, next);
+            any_free.get_or_insert(dir);
+
+            // The body after one move without eating
+            let mut body_after = vec![next];
+            body_after.extend_from_slice(&body[..body.len() - 1]);
+            let tail_after = body_after[body_after.len() - 1];
+
+            if let Some(path) = shortest_path(width, height, &body_after, tail_after) {
+                if best.is_none_or(|(_, length)| path.len() > length) {
+                    best = Some((dir, path.len()));
+                }
+            }
+        }
+
+        best.map(|(dir, _)| dir).or(any_free)
+    }
+}
+
+// Cells the head cannot move into. The tail is left free because it moves
+// out of the way on the same step, unless the snake is too short for that
+fn occupancy(width: usize, height: usize, body: &[(usize, usize)]) -> Vec<bool> {
+    let mut blocked = vec![false; width * height];
+    let blocking = if body.len() > 2 {
+        &body[1..body.len() - 1]
+    } else {
+        &body[1..]
+    };
+    for &(x, y) in blocking {
+        if x < width && y < height {
+            blocked[x + y * width] = true;
+        }
+    }
+    blocked
+}
+
+// Breadth-first search from the head of `body` to `target`. Returns the cells
+// along the way, excluding the head and including the target
+fn shortest_path(
+    width: usize,
+    height: usize,
+    body: &[(usize, usize)],
+    target: (usize, usize),
+) -> Option<Vec<(usize, usize)>> {
+    let head = body[0];
+    let blocked = occupancy(width, height, body);
+    let mut came_from: Vec<Option<(usize, usize)>> = vec![None; width * height];
+    let mut queue = VecDeque::new();
+
+    came_from[head.0 + head.1 * width] = Some(head);
+    queue.push_back(head);
+
+    while let Some(cell) = queue.pop_front() {
+        if cell == target && cell != head {
+            let mut path = vec![cell];
+            let mut cur = cell;
+            while let Some(prev) = came_from[cur.0 + cur.1 * width] {
+                if prev == head {
+                    break;
+                }
+                path.push(prev);
+                cur = prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for next in neighbours(width, height, cell) {
+            let index = next.0 + next.1 * width;
+            if came_from[index].is_none() && (!blocked[index] || next == target) {
+                came_from[index] = Some(cell);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+fn neighbours(
+    width: usize,
+    height: usize,
+    (x, y): (usize, usize),
+) -> impl Iterator<Item = (usize, usize)> {
+    Direction::ALL.into_iter().filter_map(move |direction| {
+        let (dx, dy) = direction.delta();
+        let next_x = x as i32 + dx;
+        let next_y = y as i32 + dy;
+        if next_x < 0 || next_y < 0 || next_x as usize >= width || next_y as usize >= height {
+            None
+        } else {
+            Some((next_x as usize, next_y as usize))
+        }
+    })
+}
+
+fn direction_between(from: (usize, usize), to: (usize, usize)) -> (i32, i32) {
+    (to.0 as i32 - from.0 as i32, to.1 as i32 - from.1 as i32)
+}
@@ -0,0 +1,276 @@
+use crate::bot::NOT_ON_CYCLE;
+use crate::direction::Direction;
+use crate::params::BotParams;
+use crate::strategy::{BoardView, Branch, Budget, Candidate, Decision, Strategy};
+
+// Helper function to get position index in cycle
+fn get_position_in_cycle(pos: (usize, usize), cycle: &[(usize, usize)]) -> Option<usize> {
+    cycle.iter().position(|&p| p == pos)
+}
+
+// Helper function to get tour number at a specific position. Cells the cycle
+// skips have no tour number, so the bot never plans a route through them
+fn get_tour_number(
+    position: (usize, usize),
+    tour_numbers: &[usize],
+    grid_width: usize,
+) -> Option<usize> {
+    if position.0 >= grid_width || position.1 >= tour_numbers.len() / grid_width {
+        return None;
+    }
+
+    let index = position.0 + position.1 * grid_width;
+    match tour_numbers.get(index) {
+        Some(&tour_number) if tour_number != NOT_ON_CYCLE => Some(tour_number),
+        _ => None,
+    }
+}
+
+/// Follows the Hamiltonian cycle, taking shortcuts towards the apple when the
+/// snake is short enough that skipping part of the cycle cannot trap it.
+pub struct HamiltonianShortcut {
+    params: BotParams,
+}
+
+impl HamiltonianShortcut {
+    pub fn new(params: &BotParams) -> Self {
+        Self {
+            params: params.clone(),
+        }
+    }
+}
+
+impl Strategy for HamiltonianShortcut {
+    fn set_params(&mut self, params: &BotParams) {
+        self.params = params.clone();
+    }
+
+    fn next_direction(&mut self, view: &BoardView) -> (i32, i32) {
+        self.decide(view).0
+    }
+
+    fn decide(&mut self, view: &BoardView) -> ((i32, i32), Option<Decision>) {
+        let cycle = view.cycle;
+        let apple_pos = view.apple;
+        let tour_numbers = view.tour_numbers;
+        let map_width = view.width;
+        let map_height = view.height;
+        let snake = view.snake;
+        let params = &self.params;
+
+        let head_pos = snake.segments[0].cur;
+        let current_snake_direction = snake.direction();
+
+        // Filled in as the bot works through its options, then handed back
+        // with whichever move it settles on
+        let mut decision = Decision {
+            head: head_pos,
+            budget: None,
+            candidates: Vec::new(),
+            direction: Direction::Right.delta(),
+            branch: Branch::Trapped,
+        };
+        let choose = |mut decision: Decision, direction: (i32, i32), branch: Branch| {
+            decision.direction = direction;
+            decision.branch = branch;
+            (direction, Some(decision))
+        };
+
+        // Helper for collision check. Returns true if collision, false otherwise.
+        let check_collision = |pos_to_check: (usize, usize), proposed_dir: (i32, i32)| -> bool {
+            // Boundary check
+            if pos_to_check.0 >= map_width || pos_to_check.1 >= map_height {
+                return true;
+            }
+            for i in 1..snake.segments.len() {
+                if pos_to_check == snake.segments[i].cur {
+                    return true;
+                }
+            }
+            // Prevent moving directly backward if snake length > 1
+            if snake.segments.len() > 1 && current_snake_direction != (0, 0) {
+                // current_snake_direction could be (0,0) if snake hasn't moved
+                if proposed_dir.0 == -current_snake_direction.0
+                    && proposed_dir.1 == -current_snake_direction.1
+                {
+                    return true;
+                }
+            }
+            false // No collision
+        };
+
+        let head_tour = get_tour_number(head_pos, tour_numbers, map_width);
+        let food_tour = get_tour_number(apple_pos, tour_numbers, map_width);
+        let tail_pos = snake.segments.last().expect("Snake must have segments").cur;
+        let tail_tour = get_tour_number(tail_pos, tour_numbers, map_width);
+
+        if let (false, Some(head_tour_num), Some(food_tour_num), Some(tail_tour_num)) =
+            (cycle.is_empty(), head_tour, food_tour, tail_tour)
+        {
+            // Only cells on the cycle count towards the arena
+            let arena_size = cycle.len();
+
+            let distance_to_food = if food_tour_num >= head_tour_num {
+                food_tour_num - head_tour_num
+            } else {
+                arena_size - head_tour_num + food_tour_num
+            };
+
+            let distance_to_tail = if tail_tour_num >= head_tour_num {
+                tail_tour_num - head_tour_num
+            } else {
+                arena_size - head_tour_num + tail_tour_num
+            };
+
+            let food_value = params.food_value; // Growth from one apple
+
+            let mut cutting_amount_available =
+                distance_to_tail.saturating_sub(params.growth_buffer);
+
+            let snake_drawn_length = snake.segments.len();
+            let num_empty_squares_on_board = arena_size
+                .saturating_sub(snake_drawn_length)
+                .saturating_sub(food_value);
+
+            if distance_to_food < distance_to_tail {
+                // Food is between head and tail on cycle
+                cutting_amount_available = cutting_amount_available.saturating_sub(food_value);
+                if (distance_to_tail.saturating_sub(distance_to_food)) * params.empty_square_factor
+                    > num_empty_squares_on_board
+                {
+                    cutting_amount_available =
+                        cutting_amount_available.saturating_sub(params.future_food_penalty);
+                }
+            }
+
+            let cutting_amount_desired = distance_to_food;
+            if cutting_amount_desired < cutting_amount_available {
+                cutting_amount_available = cutting_amount_desired;
+            }
+            decision.budget = Some(Budget {
+                distance_to_food,
+                distance_to_tail,
+                cutting_amount_available,
+            });
+
+            let mut best_dir_candidate: Option<(i32, i32)> = None;
+            let mut best_dist_cut = -1isize; // Maximize this value (length of shortcut on cycle)
+
+            let shortcut_eval_order = params.eval_order();
+
+            for &dir_candidate in &shortcut_eval_order {
+                let next_potential_x = head_pos.0 as i32 + dir_candidate.0;
+                let next_potential_y = head_pos.1 as i32 + dir_candidate.1;
+
+                // Cells off any edge of the board are not candidates at all
+                if next_potential_x < 0
+                    || next_potential_y < 0
+                    || next_potential_x as usize >= map_width
+                    || next_potential_y as usize >= map_height
+                {
+                    continue;
+                }
+
+                let next_potential_pos = (next_potential_x as usize, next_potential_y as usize);
+                let mut candidate = Candidate {
+                    direction: dir_candidate,
+                    cell: next_potential_pos,
+                    blocked: true,
+                    dist_on_cycle: None,
+                };
+
+                if !check_collision(next_potential_pos, dir_candidate) {
+                    candidate.blocked = false;
+                    if let Some(next_pos_tour_num) =
+                        get_tour_number(next_potential_pos, tour_numbers, map_width)
+                    {
+                        let dist_on_cycle_to_next = if next_pos_tour_num >= head_tour_num {
+                            next_pos_tour_num - head_tour_num
+                        } else {
+                            arena_size - head_tour_num + next_pos_tour_num
+                        } as isize;
+                        candidate.dist_on_cycle = Some(dist_on_cycle_to_next as usize);
+
+                        if dist_on_cycle_to_next <= cutting_amount_available as isize
+                            && dist_on_cycle_to_next > best_dist_cut
+                        {
+                            best_dist_cut = dist_on_cycle_to_next;
+                            best_dir_candidate = Some(dir_candidate);
+                        }
+                    }
+                }
+                decision.candidates.push(candidate);
+            }
+
+            if let Some(dir) = best_dir_candidate {
+                return choose(decision, dir, Branch::Shortcut);
+            }
+
+            // Fallback: Follow the Hamiltonian cycle by finding the next position in the tour
+            let next_tour_num = (head_tour_num + 1) % arena_size;
+            for &dir_candidate in &shortcut_eval_order {
+                let next_potential_x = head_pos.0 as i32 + dir_candidate.0;
+                let next_potential_y = head_pos.1 as i32 + dir_candidate.1;
+
+                if next_potential_x < 0
+                    || next_potential_y < 0
+                    || next_potential_x as usize >= map_width
+                    || next_potential_y as usize >= map_height
+                {
+                    continue;
+                }
+
+                let next_potential_pos = (next_potential_x as usize, next_potential_y as usize);
+
+                if !check_collision(next_potential_pos, dir_candidate) {
+                    if let Some(tour_num) =
+                        get_tour_number(next_potential_pos, tour_numbers, map_width)
+                    {
+                        if tour_num == next_tour_num {
+                            return choose(decision, dir_candidate, Branch::FollowTour);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Fallback: Try default Hamiltonian cycle move (using positions)
+        if !cycle.is_empty() {
+            if let Some(head_idx) = get_position_in_cycle(head_pos, cycle) {
+                let next_target_idx = (head_idx + 1) % cycle.len();
+                let next_target_pos = cycle[next_target_idx];
+                let default_cycle_dir = (
+                    next_target_pos.0 as i32 - head_pos.0 as i32,
+                    next_target_pos.1 as i32 - head_pos.1 as i32,
+                );
+                if !check_collision(next_target_pos, default_cycle_dir) {
+                    return choose(decision, default_cycle_dir, Branch::FollowCycle);
+                }
+            }
+        }
+
+        let fallback_moves_ordered = [
+            Direction::Up,
+            Direction::Left,
+            Direction::Down,
+            Direction::Right,
+        ]
+        .map(Direction::delta);
+
+        for &fallback_dir in &fallback_moves_ordered {
+            let next_potential_x = head_pos.0 as i32 + fallback_dir.0;
+            let next_potential_y = head_pos.1 as i32 + fallback_dir.1;
+
+            if next_potential_x < 0 || next_potential_y < 0 {
+                continue;
+            }
+            let next_potential_pos = (next_potential_x as usize, next_potential_y as usize);
+            if !check_collision(next_potential_pos, fallback_dir) {
+                return choose(decision, fallback_dir, Branch::AnySafeMove);
+            }
+        }
+
+        let trapped_dir = Direction::Right.delta();
+        choose(decision, trapped_dir, Branch::Trapped)
+    }
+}
@@ -0,0 +1,436 @@
+use std::any::Any;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bot::{check_cycle, NOT_ON_CYCLE};
+use crate::params::BotParams;
+use crate::strategy::{BoardView, Decision, HamiltonianShortcut, Strategy};
+
+/// Follows its own copy of the Hamiltonian cycle and reshapes it before every
+/// move, so the apple comes up as soon as possible after the head.
+///
+/// The cycle is reshaped with 2x2 splices. Wherever two opposite sides of a 2x2
+/// square are cycle edges (on a grid they always run in opposite directions),
+/// swapping them for the other two sides cuts the stretch of cycle between them
+/// off into a loop of its own. Doing the same at a second square, with one side
+/// on the loop and the opposite side on the rest of the cycle, joins the loop
+/// back in there. Loops are only ever cut from between the head and the apple
+/// and joined back in between the apple and the tail, so the body keeps its
+/// order along the cycle and the shortcut rules stay as safe as before.
+pub struct CycleRepair {
+    // Shared with the checkpoints taken for stepping back, and copied only
+    // when a repair changes it
+    state: Rc<RepairState>,
+    // Where the apple and the tail were when the last repair ran out of
+    // splices, or None if the next one has to look everywhere again
+    scanned: Option<Scanned>,
+    shortcuts: HamiltonianShortcut,
+}
+
+struct Scanned {
+    apple: (usize, usize),
+    tail_tour: usize,
+    // The loops that could be cut then, as the tour numbers of their first
+    // and last cells
+    cuts: Vec<(usize, usize)>,
+}
+
+// The reshaped cycle, which is all the bot builds up over a game
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct RepairState {
+    cycle: Vec<(usize, usize)>,
+    tour_numbers: Vec<usize>,
+    width: usize,
+}
+
+// A loop that can be moved: the stretch `start..=end` of the cycle (positions
+// counted from the head), and where to join it back in
+struct Splice {
+    start: usize,
+    end: usize,
+    // The loop is entered here when joined back in, and left from the cell before
+    loop_entry: usize,
+    // The cycle cell the loop is joined in after
+    join_after: usize,
+}
+
+impl CycleRepair {
+    pub fn new(params: &BotParams) -> Self {
+        Self {
+            state: Rc::default(),
+            scanned: None,
+            shortcuts: HamiltonianShortcut::new(params),
+        }
+    }
+
+    fn tour_number(&self, cell: (usize, usize)) -> usize {
+        self.state.tour_numbers[cell.0 + cell.1 * self.state.width]
+    }
+
+    // Distance along the cycle from the head to `cell`
+    fn relative_position(&self, head: (usize, usize), cell: (usize, usize)) -> usize {
+        let n = self.state.cycle.len();
+        (self.tour_number(cell) + n - self.tour_number(head)) % n
+    }
+
+    // Cycle position of the tail relative to the head, if the body is laid out
+    // along the cycle in order (each segment further behind the head than the
+    // one before it). Until it is, the cycle cannot be reshaped safely
+    fn ordered_tail_position(&self, view: &BoardView) -> Option<usize> {
+        let head = view.snake.segments[0].cur;
+        let mut previous = self.state.cycle.len();
+        for segment in &view.snake.segments[1..] {
+            let (x, y) = segment.cur;
+            if x >= view.width || y >= view.height || self.tour_number(segment.cur) == NOT_ON_CYCLE
+            {
+                return None;
+            }
+            let position = self.relative_position(head, segment.cur);
+            if position == 0 || position >= previous {
+                return None;
+            }
+            previous = position;
+        }
+        Some(previous)
+    }
+
+    // Whether a repair could find a splice the last one did not. While the
+    // apple stays put, the head moving on only leaves fewer loops to cut in
+    // front of it, and the tail moving on only leaves more edges to join them
+    // in at. So once a repair has run out of splices, only those newly freed
+    // edges can start it up again, and only if one of them faces an edge on a
+    // loop that can still be cut
+    fn worth_repairing(&self, view: &BoardView, tail_position: usize) -> bool {
+        let Some(scanned) = &self.scanned else {
+            return true;
+        };
+        if scanned.apple != view.apple {
+            return true;
+        }
+        let ahead = Ahead {
+            cycle: &self.state.cycle,
+            tour_numbers: &self.state.tour_numbers,
+            width: self.state.width,
+            height: view.height,
+            head_tour: self.tour_number(view.snake.segments[0].cur),
+        };
+        let (Some(apple), Some(old_tail)) = (
+            ahead.position(view.apple),
+            ahead.position(self.state.cycle[scanned.tail_tour]),
+        ) else {
+            return true;
+        };
+        if old_tail > tail_position {
+            return true;
+        }
+        // Loops only join in at edges that start at or after the apple
+        let freed = old_tail.max(apple) + 1..=tail_position;
+        if freed.is_empty() {
+            return false;
+        }
+        let cuts: Vec<(usize, usize)> = scanned
+            .cuts
+            .iter()
+            .map(|&(start, end)| {
+                let position = |tour| ahead.position(self.state.cycle[tour]);
+                (position(start), position(end))
+            })
+            .filter_map(|cut| match cut {
+                // Loops the head has reached can no longer be cut
+                (Some(start), Some(end)) if 1 <= start && start <= end && end <= apple => {
+                    Some((start, end))
+                }
+                _ => None,
+            })
+            .collect();
+        freed.into_iter().any(|y| {
+            across(ahead.cell(y), ahead.cell(y - 1)).any(|(p, q)| {
+                let (Some(p), Some(q)) = (ahead.position(p), ahead.position(q)) else {
+                    return false;
+                };
+                cuts.iter().any(|&(start, end)| {
+                    (start <= p && p < end && q == p + 1) || (p == end && q == start)
+                })
+            })
+        })
+    }
+
+    // Move loops from in front of the apple to behind it, largest first, until
+    // no more can be moved. Returns the loops left that could be cut but not
+    // joined back in, as tour numbers
+    fn repair(&mut self, view: &BoardView, tail_position: usize) -> Vec<(usize, usize)> {
+        let width = self.state.width;
+        let n = self.state.cycle.len();
+        let head_tour = self.tour_number(view.snake.segments[0].cur);
+
+        loop {
+            let ahead = Ahead {
+                cycle: &self.state.cycle,
+                tour_numbers: &self.state.tour_numbers,
+                width,
+                height: view.height,
+                head_tour,
+            };
+            let Some(apple) = ahead.position(view.apple) else {
+                return Vec::new();
+            };
+            let cuts = find_cuts(&ahead, apple);
+            let Some(splice) = find_splice(&ahead, &cuts, apple, tail_position) else {
+                let tour = |i| (head_tour + i) % n;
+                return cuts
+                    .into_iter()
+                    .map(|(start, end)| (tour(start), tour(end)))
+                    .collect();
+            };
+            let Splice {
+                start,
+                end,
+                loop_entry,
+                join_after,
+            } = splice;
+
+            // Up to the cut, on to the join, round the loop, then the rest
+            let cells = |range: std::ops::Range<usize>| range.map(|i| ahead.cell(i));
+            let reshaped: Vec<(usize, usize)> = cells(0..start)
+                .chain(cells(end + 1..join_after + 1))
+                .chain(cells(loop_entry..end + 1))
+                .chain(cells(start..loop_entry))
+                .chain(cells(join_after + 1..n))
+                .collect();
+
+            // The head keeps its tour number, so the cycle only changes in front of it
+            let state = Rc::make_mut(&mut self.state);
+            for (i, cell) in reshaped.into_iter().enumerate() {
+                state.cycle[(head_tour + i) % n] = cell;
+            }
+            for (i, &(x, y)) in state.cycle.iter().enumerate() {
+                state.tour_numbers[x + y * width] = i;
+            }
+        }
+    }
+}
+
+// The cycle laid out from the head onwards, read straight off the tour numbers
+// so that looking for a splice copies nothing
+struct Ahead<'a> {
+    cycle: &'a [(usize, usize)],
+    tour_numbers: &'a [usize],
+    width: usize,
+    height: usize,
+    head_tour: usize,
+}
+
+impl Ahead<'_> {
+    // The cell `i` moves along the cycle from the head
+    fn cell(&self, i: usize) -> (usize, usize) {
+        self.cycle[(self.head_tour + i) % self.cycle.len()]
+    }
+
+    // How far along the cycle from the head `cell` is, if it is on the cycle
+    fn position(&self, (x, y): (usize, usize)) -> Option<usize> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let tour = self.tour_numbers[x + y * self.width];
+        let n = self.cycle.len();
+        (tour != NOT_ON_CYCLE).then(|| (tour + n - self.head_tour) % n)
+    }
+}
+
+// The edges facing the edge from `a` to `b` across a 2x2 square, one on each side
+fn across(
+    a: (usize, usize),
+    b: (usize, usize),
+) -> impl Iterator<Item = ((usize, usize), (usize, usize))> {
+    let (dx, dy) = (b.1 as i32 - a.1 as i32, b.0 as i32 - a.0 as i32);
+    [(dx, dy), (-dx, -dy)]
+        .into_iter()
+        .filter_map(move |(dx, dy)| {
+            let shift = |(x, y): (usize, usize)| {
+                let (x, y) = (x as i32 + dx, y as i32 + dy);
+                (x >= 0 && y >= 0).then_some((x as usize, y as usize))
+            };
+            Some((shift(a)?, shift(b)?))
+        })
+}
+
+// The loops between the head and the apple that can be cut out, largest
+// first: wherever an edge i -> i + 1 faces an edge j -> j + 1 that runs back
+// the other way, both in front of the apple, the loop is i + 1..=j
+fn find_cuts(ahead: &Ahead, apple: usize) -> Vec<(usize, usize)> {
+    let mut cuts = Vec::new();
+    for i in 0..apple {
+        for (a, b) in across(ahead.cell(i), ahead.cell(i + 1)) {
+            if let (Some(a), Some(b)) = (ahead.position(a), ahead.position(b)) {
+                if a == b + 1 && b > i && a <= apple {
+                    cuts.push((i + 1, b));
+                }
+            }
+        }
+    }
+    cuts.sort_by_key(|&(start, end)| std::cmp::Reverse(end - start));
+    cuts
+}
+
+// Find the longest of the `cuts` that can be joined back in somewhere between
+// the apple and the tail
+fn find_splice(
+    ahead: &Ahead,
+    cuts: &[(usize, usize)],
+    apple: usize,
+    tail_position: usize,
+) -> Option<Splice> {
+    let position_of = |cell| ahead.position(cell);
+
+    // Joins: a loop edge p -> q facing a cycle edge x -> y between the apple
+    // and the tail, with x next to q and y next to p
+    let join_at = |p: (usize, usize), q: (usize, usize)| {
+        across(p, q).find_map(|(y, x)| {
+            let (y, x) = (position_of(y)?, position_of(x)?);
+            (y == x + 1 && x >= apple && y <= tail_position).then_some(x)
+        })
+    };
+    // Every loop edge but the one closing the loop is a cycle edge in front of
+    // the apple, so look those up once rather than again for every cut: the
+    // first edge t -> t + 1 at or after each t that a loop can be joined in at
+    let mut next_join = vec![None; apple + 1];
+    for t in (0..apple).rev() {
+        next_join[t] = match join_at(ahead.cell(t), ahead.cell(t + 1)) {
+            Some(x) => Some((t, x)),
+            None => next_join[t + 1],
+        };
+    }
+
+    for &(start, end) in cuts {
+        let (t, join_after) = match next_join[start] {
+            Some((t, x)) if t < end => (t, x),
+            // The edge closing the loop, from its end back to its start
+            _ => match join_at(ahead.cell(end), ahead.cell(start)) {
+                Some(x) => (end, x),
+                None => continue,
+            },
+        };
+        return Some(Splice {
+            start,
+            end,
+            loop_entry: if t == end { start } else { t + 1 },
+            join_after,
+        });
+    }
+
+    None
+}
+
+impl Strategy for CycleRepair {
+    fn set_params(&mut self, params: &BotParams) {
+        self.shortcuts.set_params(params);
+    }
+
+    fn next_direction(&mut self, view: &BoardView) -> (i32, i32) {
+        self.decide(view).0
+    }
+
+    fn decide(&mut self, view: &BoardView) -> ((i32, i32), Option<Decision>) {
+        if self.state.cycle.len() != view.cycle.len() || self.state.width != view.width {
+            self.state = Rc::new(RepairState {
+                cycle: view.cycle.to_vec(),
+                tour_numbers: view.tour_numbers.to_vec(),
+                width: view.width,
+            });
+            self.scanned = None;
+        }
+
+        let (x, y) = view.snake.segments[0].cur;
+        let head_on_cycle =
+            x < view.width && y < view.height && self.tour_number((x, y)) != NOT_ON_CYCLE;
+
+        // The tail frees up room to join loops into on every move, so keep
+        // repairing rather than only when the apple moves, but skip the moves
+        // that cannot turn up anything new
+        if head_on_cycle {
+            if let Some(tail_position) = self.ordered_tail_position(view) {
+                if self.worth_repairing(view, tail_position) {
+                    let cuts = self.repair(view, tail_position);
+                    let tail = view.snake.segments[view.snake.segments.len() - 1].cur;
+                    self.scanned = Some(Scanned {
+                        apple: view.apple,
+                        tail_tour: self.tour_number(tail),
+                        cuts,
+                    });
+                }
+            }
+        } else {
+            self.scanned = None;
+        }
+
+        // Move along the reshaped cycle, still taking any safe shortcuts on it
+        let repaired_view = BoardView {
+            cycle: &self.state.cycle,
+            tour_numbers: &self.state.tour_numbers,
+            ..*view
+        };
+        self.shortcuts.decide(&repaired_view)
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(&*self.state).ok()
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        let state: RepairState = serde_json::from_value(state).map_err(|err| err.to_string())?;
+        let RepairState {
+            cycle,
+            tour_numbers,
+            width,
+        } = &state;
+        if *width == 0 || !tour_numbers.len().is_multiple_of(*width) {
+            return Err("the repaired cycle does not fit its board".to_owned());
+        }
+        let height = tour_numbers.len() / width;
+        check_cycle(cycle, tour_numbers, *width, height)
+            .map_err(|err| format!("the repaired cycle is broken: {}", err))?;
+        self.state = Rc::new(state);
+        self.scanned = None;
+        Ok(())
+    }
+
+    fn checkpoint(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(Rc::clone(&self.state)))
+    }
+
+    fn rewind(&mut self, checkpoint: Box<dyn Any>) {
+        if let Ok(state) = checkpoint.downcast::<Rc<RepairState>>() {
+            self.state = *state;
+            self.scanned = None;
+        }
+    }
+
+    // Nothing to show until the first move has copied the game's cycle
+    fn cycle(&self) -> Option<&[(usize, usize)]> {
+        (!self.state.cycle.is_empty()).then_some(&self.state.cycle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::Simulation;
+
+    #[test]
+    fn skipping_repairs_changes_nothing() {
+        for seed in 0..3 {
+            let mut sim = Simulation::new(12, 12, seed).unwrap();
+            let mut skipping = CycleRepair::new(&BotParams::default());
+            let mut every_move = CycleRepair::new(&BotParams::default());
+            while !sim.is_over() {
+                // Forgetting the last scan makes the bot repair again
+                every_move.scanned = None;
+                let direction = every_move.decide(&sim.view()).0;
+                assert_eq!(skipping.decide(&sim.view()).0, direction);
+                assert_eq!(skipping.state.cycle, every_move.state.cycle);
+                sim.step_towards(direction);
+            }
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Every colour the game draws with. Themes are picked by name from the
+/// built-in palettes or from `[themes.<name>]` tables in the settings file,
+/// where colours are written `"#rrggbb"`, or `"#rrggbbaa"` to see through.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    #[serde(with = "hex_color")]
+    pub background: Color,
+    /// Cell outlines, left undrawn when fully transparent.
+    #[serde(with = "hex_color")]
+    pub grid: Color,
+    /// The cell the cycle leaves out of a board with two odd sides.
+    #[serde(with = "hex_color")]
+    pub skipped_cell: Color,
+    #[serde(with = "hex_color")]
+    pub snake: Color,
+    #[serde(with = "hex_color")]
+    pub apple: Color,
+    #[serde(with = "hex_color")]
+    pub cycle: Color,
+    #[serde(with = "hex_color")]
+    pub text: Color,
+    /// Sliders, checkboxes, buttons and unselected menu entries.
+    #[serde(with = "hex_color")]
+    pub control: Color,
+    /// Behind the controls panels and over a finished game.
+    #[serde(with = "hex_color")]
+    pub panel: Color,
+    /// The heatmap runs from `heat_near` just ahead of the head to `heat_far`
+    /// just behind it.
+    #[serde(with = "hex_color")]
+    pub heat_near: Color,
+    #[serde(with = "hex_color")]
+    pub heat_far: Color,
+    #[serde(with = "hex_color")]
+    pub tour_numbers: Color,
+    #[serde(with = "hex_color")]
+    pub head_marker: Color,
+    #[serde(with = "hex_color")]
+    pub tail_marker: Color,
+    #[serde(with = "hex_color")]
+    pub apple_marker: Color,
+    /// Decision overlay: candidates within the bot's budget.
+    #[serde(with = "hex_color")]
+    pub within_budget: Color,
+    /// Decision overlay: candidates that would cut too far.
+    #[serde(with = "hex_color")]
+    pub over_budget: Color,
+    #[serde(with = "hex_color")]
+    pub blocked: Color,
+    #[serde(with = "hex_color")]
+    pub off_cycle: Color,
+    /// Decision overlay: the move the bot made.
+    #[serde(with = "hex_color")]
+    pub chosen: Color,
+}
+
+// Custom themes start from classic, so they only need the colours they change
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::classic()
+    }
+}
+
+impl Theme {
+    /// The built-in palettes, in the order the theme selector goes through them.
+    pub const BUILT_IN: [&'static str; 4] = ["classic", "dark", "high-contrast", "colour-blind"];
+
+    /// Looks a theme up by name: one defined in the settings file, or else a
+    /// built-in one.
+    pub fn find(name: &str, custom: &BTreeMap<String, Theme>) -> Option<Theme> {
+        if let Some(theme) = custom.get(name) {
+            return Some(*theme);
+        }
+        match name {
+            "classic" => Some(Theme::classic()),
+            "dark" => Some(Theme::dark()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            "colour-blind" => Some(Theme::colour_blind()),
+            _ => None,
+        }
+    }
+
+    /// Every theme name there is to pick from: the built-in ones, then the
+    /// settings file's own.
+    pub fn names(custom: &BTreeMap<String, Theme>) -> Vec<String> {
+        let mut names: Vec<String> = Self::BUILT_IN.iter().map(|&name| name.to_owned()).collect();
+        names.extend(
+            custom
+                .keys()
+                .filter(|name| !Self::BUILT_IN.contains(&name.as_str()))
+                .cloned(),
+        );
+        names
+    }
+
+    /// The original look: green snake and red apple on black.
+    pub fn classic() -> Theme {
+        Theme {
+            background: BLACK,
+            grid: BLANK,
+            skipped_cell: DARKGRAY,
+            snake: GREEN,
+            apple: RED,
+            cycle: RED,
+            text: WHITE,
+            control: GRAY,
+            panel: Color::new(0.0, 0.0, 0.0, 0.5),
+            heat_near: Color::new(1.0, 0.2, 0.0, 0.45),
+            heat_far: Color::new(0.0, 0.2, 1.0, 0.45),
+            tour_numbers: LIGHTGRAY,
+            head_marker: WHITE,
+            tail_marker: SKYBLUE,
+            apple_marker: YELLOW,
+            within_budget: GREEN,
+            over_budget: ORANGE,
+            blocked: RED,
+            off_cycle: GRAY,
+            chosen: GOLD,
+        }
+    }
+
+    /// Muted colours on a charcoal board with a faint grid.
+    pub fn dark() -> Theme {
+        Theme {
+            background: Color::from_hex(0x121212),
+            grid: Color::from_hex(0x1f1f1f),
+            skipped_cell: Color::from_hex(0x2c2c2c),
+            snake: Color::from_hex(0x5fa463),
+            apple: Color::from_hex(0xd9574f),
+            cycle: Color::from_hex(0x7a3f3c),
+            text: Color::from_hex(0xd8d8d8),
+            control: Color::from_hex(0x4a4a4a),
+            panel: Color::from_rgba(0x1a, 0x1a, 0x1a, 0xcc),
+            heat_near: Color::from_rgba(0xb5, 0x4a, 0x2c, 0x73),
+            heat_far: Color::from_rgba(0x2c, 0x4a, 0x8f, 0x73),
+            tour_numbers: Color::from_hex(0x8a8a8a),
+            head_marker: Color::from_hex(0xd8d8d8),
+            tail_marker: Color::from_hex(0x6f9fd8),
+            apple_marker: Color::from_hex(0xd8c25a),
+            within_budget: Color::from_hex(0x5fa463),
+            over_budget: Color::from_hex(0xc98a3c),
+            blocked: Color::from_hex(0xd9574f),
+            off_cycle: Color::from_hex(0x5a5a5a),
+            chosen: Color::from_hex(0xe0c060),
+        }
+    }
+
+    /// Pure, saturated colours on black for the strongest contrast.
+    pub fn high_contrast() -> Theme {
+        Theme {
+            background: BLACK,
+            grid: Color::from_hex(0x303030),
+            skipped_cell: Color::from_hex(0x606060),
+            snake: Color::from_hex(0xffff00),
+            apple: Color::from_hex(0xff00ff),
+            cycle: Color::from_hex(0x00ffff),
+            text: WHITE,
+            control: Color::from_hex(0x808080),
+            panel: Color::from_rgba(0x00, 0x00, 0x00, 0xe6),
+            heat_near: Color::from_rgba(0xff, 0x00, 0x00, 0x80),
+            heat_far: Color::from_rgba(0x00, 0x00, 0xff, 0x80),
+            tour_numbers: WHITE,
+            head_marker: WHITE,
+            tail_marker: Color::from_hex(0x00ffff),
+            apple_marker: Color::from_hex(0xff00ff),
+            within_budget: Color::from_hex(0x00ff00),
+            over_budget: Color::from_hex(0xff8000),
+            blocked: Color::from_hex(0xff0000),
+            off_cycle: Color::from_hex(0x808080),
+            chosen: WHITE,
+        }
+    }
+
+    /// The Okabe-Ito palette, which stays distinct under the common forms of
+    /// colour blindness. Nothing relies on telling red from green.
+    pub fn colour_blind() -> Theme {
+        Theme {
+            background: Color::from_hex(0x101010),
+            grid: BLANK,
+            skipped_cell: Color::from_hex(0x404040),
+            snake: Color::from_hex(0x56b4e9),
+            apple: Color::from_hex(0xe69f00),
+            cycle: Color::from_hex(0xcc79a7),
+            text: WHITE,
+            control: Color::from_hex(0x808080),
+            panel: Color::from_rgba(0x00, 0x00, 0x00, 0x99),
+            heat_near: Color::from_rgba(0xe6, 0x9f, 0x00, 0x73),
+            heat_far: Color::from_rgba(0x00, 0x72, 0xb2, 0x73),
+            tour_numbers: Color::from_hex(0xbbbbbb),
+            head_marker: WHITE,
+            tail_marker: Color::from_hex(0x0072b2),
+            apple_marker: Color::from_hex(0xf0e442),
+            within_budget: Color::from_hex(0x009e73),
+            over_budget: Color::from_hex(0xf0e442),
+            blocked: Color::from_hex(0xd55e00),
+            off_cycle: Color::from_hex(0x999999),
+            chosen: WHITE,
+        }
+    }
+}
+
+// Colours as "#rrggbb" or "#rrggbbaa" strings
+mod hex_color {
+    use macroquad::color::Color;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b, a]: [u8; 4] = (*color).into();
+        let text = if a == 255 {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        };
+        serializer.serialize_str(&text)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let hex = text
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6 || hex.len() == 8)
+            .and_then(|hex| Some((hex.len(), u32::from_str_radix(hex, 16).ok()?)));
+        match hex {
+            Some((6, rgb)) => Ok(Color::from_hex(rgb)),
+            Some((_, rgba)) => {
+                let [r, g, b, a] = rgba.to_be_bytes();
+                Ok(Color::from_rgba(r, g, b, a))
+            }
+            None => Err(serde::de::Error::custom(format!(
+                "expected a colour like \"#1e90ff\" or \"#1e90ff80\", got {:?}",
+                text
+            ))),
+        }
+    }
+}